@@ -0,0 +1,85 @@
+use criterion::{BenchmarkId, Criterion};
+use std::{fmt::Debug, fs, path::Path};
+
+use anyhow::Context;
+
+// A harness for comparing alternative implementations of the same part of
+// a day (e.g., the original and refactored versions of day 5, part 2). All
+// the implementations take the contents of an input file. We first check
+// that they all agree on every input, and then benchmark them against each
+// other in a single criterion group so they show up side-by-side in the
+// reports.
+pub struct Comparison<O> {
+    group_name: &'static str,
+    implementations: Vec<Implementation<O>>,
+}
+
+type Implementation<O> = (&'static str, fn(&str) -> O);
+
+impl<O: PartialEq + Debug> Comparison<O> {
+    pub const fn new(group_name: &'static str) -> Self {
+        Self {
+            group_name,
+            implementations: Vec::new(),
+        }
+    }
+
+    pub fn register(mut self, name: &'static str, implementation: fn(&str) -> O) -> Self {
+        self.implementations.push((name, implementation));
+        self
+    }
+
+    // Panics (which fails the benchmark run) if any implementation
+    // disagrees with the first one.
+    pub fn assert_agree(&self, input_name: &str, contents: &str) {
+        let results = self
+            .implementations
+            .iter()
+            .map(|(name, implementation)| (*name, implementation(contents)))
+            .collect::<Vec<_>>();
+        let (first_name, first_result) = &results[0];
+        for (name, result) in &results[1..] {
+            assert_eq!(
+                first_result, result,
+                "'{name}' disagrees with '{first_name}' on '{input_name}': {results:?}"
+            );
+        }
+        println!(
+            "All {} implementations of '{}' agree on '{input_name}': {first_result:?}",
+            results.len(),
+            self.group_name
+        );
+    }
+
+    pub fn bench(&self, c: &mut Criterion, input_files: &[&str]) {
+        let inputs = input_files
+            .iter()
+            .map(|input_file| {
+                let contents = fs::read_to_string(input_file)
+                    .with_context(|| format!("Failed to open file '{input_file}'"))
+                    .unwrap();
+                self.assert_agree(input_file, &contents);
+                (input_name(input_file), contents)
+            })
+            .collect::<Vec<_>>();
+
+        let mut group = c.benchmark_group(self.group_name);
+        for (input_name, contents) in &inputs {
+            for (name, implementation) in &self.implementations {
+                group.bench_with_input(
+                    BenchmarkId::new(*name, input_name),
+                    contents.as_str(),
+                    |b, contents| b.iter(|| implementation(contents)),
+                );
+            }
+        }
+        group.finish();
+    }
+}
+
+// "../inputs/day_13_test.input" -> "day_13_test"
+fn input_name(input_file: &str) -> String {
+    Path::new(input_file)
+        .file_stem()
+        .map_or_else(|| input_file.to_string(), |s| s.to_string_lossy().into())
+}
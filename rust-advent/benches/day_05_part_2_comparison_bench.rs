@@ -0,0 +1,285 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+mod comparison;
+use comparison::Comparison;
+
+// The version from `src/bin/day_05_part_2.rs`, which collects the crates
+// being moved into an intermediate `Vec<char>`.
+mod original {
+    use anyhow::{ensure, Context, Result};
+    use std::str::FromStr;
+
+    const NUM_STACKS: usize = 9;
+
+    #[derive(Default, Debug)]
+    struct Stacks {
+        stacks: [Vec<char>; NUM_STACKS],
+    }
+
+    fn extract_stack_elements(line: &str) -> Vec<char> {
+        let line = line.chars().collect::<Vec<_>>();
+        (0..NUM_STACKS).map(|pos| line[1 + 4 * pos]).collect()
+    }
+
+    impl FromStr for Stacks {
+        type Err = anyhow::Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let stacks = s
+                .lines()
+                .rev()
+                .skip(1)
+                .map(extract_stack_elements)
+                .fold(Self::default(), |stacks, line| {
+                    stacks.push_values_on_stacks(&line)
+                });
+
+            Ok(stacks)
+        }
+    }
+
+    impl Stacks {
+        fn push_values_on_stacks(self, line: &[char]) -> Self {
+            line.iter()
+                .enumerate()
+                .filter(|&(_, c)| *c != ' ')
+                .fold(self, |mut stacks, (i, c)| {
+                    stacks.stacks[i].push(*c);
+                    stacks
+                })
+        }
+
+        fn apply(
+            mut self,
+            Instruction {
+                num_to_move,
+                from_stack,
+                to_stack,
+            }: Instruction,
+        ) -> Result<Self> {
+            let source = &mut self.stacks[from_stack - 1];
+            ensure!(
+                num_to_move <= source.len(),
+                "We tried to take {num_to_move} items from {source:?}"
+            );
+            let crates_to_move = source
+                .drain((source.len() - num_to_move)..)
+                .collect::<Vec<_>>();
+            let destination = &mut self.stacks[to_stack - 1];
+            destination.extend(crates_to_move);
+            Ok(self)
+        }
+
+        fn tops_string(&self) -> Result<String> {
+            self.stacks
+                .iter()
+                .map(|s| {
+                    s.last().copied().with_context(|| {
+                        format!("We tried to take the top of an empty stack: {self:?}")
+                    })
+                })
+                .collect::<Result<String>>()
+        }
+    }
+
+    struct Instruction {
+        num_to_move: usize,
+        from_stack: usize,
+        to_stack: usize,
+    }
+
+    impl FromStr for Instruction {
+        type Err = anyhow::Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let parts: Vec<usize> = s
+                .split_ascii_whitespace()
+                .enumerate()
+                .filter(|(pos, _)| pos % 2 == 1)
+                .map(|(_, val)| {
+                    val.parse::<usize>()
+                        .with_context(|| format!("Couldn't parse '{val}' to an int"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            ensure!(
+                parts.len() == 3,
+                "Line '{s}' didn't have the appropriate format"
+            );
+            Ok(Self {
+                num_to_move: parts[0],
+                from_stack: parts[1],
+                to_stack: parts[2],
+            })
+        }
+    }
+
+    pub fn top_of_stacks(contents: &str) -> String {
+        let (stack_config, instructions) = contents.split_once("\n\n").unwrap();
+        let stacks: Stacks = stack_config.parse().unwrap();
+        instructions
+            .lines()
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+            .into_iter()
+            .try_fold(stacks, Stacks::apply)
+            .unwrap()
+            .tops_string()
+            .unwrap()
+    }
+}
+
+// The version from `src/bin/day_05_part_2_refactored.rs`, which uses
+// `split_at_mut()` to move the crates directly from one stack to the other.
+mod refactored {
+    use anyhow::{ensure, Context, Result};
+    use std::str::FromStr;
+
+    const NUM_STACKS: usize = 9;
+
+    #[derive(Default, Debug)]
+    struct Stacks {
+        stacks: [Vec<char>; NUM_STACKS],
+    }
+
+    fn extract_stack_elements(line: &str) -> Vec<char> {
+        let line = line.chars().collect::<Vec<_>>();
+        (0..NUM_STACKS).map(|pos| line[1 + 4 * pos]).collect()
+    }
+
+    impl FromStr for Stacks {
+        type Err = anyhow::Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let stacks = s
+                .lines()
+                .rev()
+                .skip(1)
+                .map(extract_stack_elements)
+                .fold(Self::default(), |stacks, line| {
+                    stacks.push_values_on_stacks(&line)
+                });
+
+            Ok(stacks)
+        }
+    }
+
+    impl Stacks {
+        fn push_values_on_stacks(self, line: &[char]) -> Self {
+            line.iter()
+                .enumerate()
+                .filter(|&(_, c)| *c != ' ')
+                .fold(self, |mut stacks, (i, c)| {
+                    stacks.stacks[i].push(*c);
+                    stacks
+                })
+        }
+
+        fn get_two_mut<T>(source: &mut [T], index1: usize, index2: usize) -> (&mut T, &mut T) {
+            assert!(index1 != index2);
+            if index1 < index2 {
+                let (left, right) = source.split_at_mut(index2);
+                (&mut left[index1], &mut right[0])
+            } else {
+                let (left, right) = source.split_at_mut(index1);
+                (&mut right[0], &mut left[index2])
+            }
+        }
+
+        fn apply(
+            mut self,
+            Instruction {
+                num_to_move,
+                from_stack,
+                to_stack,
+            }: Instruction,
+        ) -> Result<Self> {
+            let (source, destination) =
+                Self::get_two_mut(&mut self.stacks, from_stack - 1, to_stack - 1);
+
+            ensure!(
+                num_to_move <= source.len(),
+                "We tried to take {num_to_move} items from {source:?}"
+            );
+
+            let crates_to_move = source.drain((source.len() - num_to_move)..);
+            destination.extend(crates_to_move);
+            Ok(self)
+        }
+
+        fn tops_string(&self) -> Result<String> {
+            self.stacks
+                .iter()
+                .map(|s| {
+                    s.last().copied().with_context(|| {
+                        format!("We tried to take the top of an empty stack: {self:?}")
+                    })
+                })
+                .collect::<Result<String>>()
+        }
+    }
+
+    struct Instruction {
+        num_to_move: usize,
+        from_stack: usize,
+        to_stack: usize,
+    }
+
+    impl FromStr for Instruction {
+        type Err = anyhow::Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let parts: Vec<usize> = s
+                .split_ascii_whitespace()
+                .enumerate()
+                .filter(|(pos, _)| pos % 2 == 1)
+                .map(|(_, val)| {
+                    val.parse::<usize>()
+                        .with_context(|| format!("Couldn't parse '{val}' to an int"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            ensure!(
+                parts.len() == 3,
+                "Line '{s}' didn't have the appropriate format"
+            );
+            ensure!(
+                parts[1] != parts[2],
+                "The source {} and destination {} stacks must be different",
+                parts[1],
+                parts[2],
+            );
+            Ok(Self {
+                num_to_move: parts[0],
+                from_stack: parts[1],
+                to_stack: parts[2],
+            })
+        }
+    }
+
+    pub fn top_of_stacks(contents: &str) -> String {
+        let (stack_config, instructions) = contents.split_once("\n\n").unwrap();
+        let stacks: Stacks = stack_config.parse().unwrap();
+        instructions
+            .lines()
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+            .into_iter()
+            .try_fold(stacks, Stacks::apply)
+            .unwrap()
+            .tops_string()
+            .unwrap()
+    }
+}
+
+static INPUT_FILES: &[&str] = &["../inputs/day_05.input"];
+
+fn top_of_stacks_comparison(c: &mut Criterion) {
+    Comparison::new("day_05_part_2")
+        .register("original", original::top_of_stacks)
+        .register("refactored", refactored::top_of_stacks)
+        .bench(c, INPUT_FILES);
+}
+
+criterion_group!(day_05_part_2_comparison_bench, top_of_stacks_comparison);
+criterion_main!(day_05_part_2_comparison_bench);
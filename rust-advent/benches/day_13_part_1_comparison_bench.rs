@@ -0,0 +1,174 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+mod comparison;
+use comparison::Comparison;
+
+// We can only have one `PartialOrd` implementation per type, so each of the
+// versions of the comparison gets its own module with its own `Packet` type.
+// Everything except the comparison (the parsing and the sum) is the same,
+// and comes from `src/bin/day_13_part_1.rs`.
+macro_rules! packet_module {
+    ($module:ident, $($comparison:item)*) => {
+        mod $module {
+            #[allow(unused_imports)]
+            use std::cmp::Ordering;
+
+            use nom::{
+                branch::alt,
+                bytes::complete::tag,
+                character::complete::{char, newline, u8},
+                combinator::map,
+                multi::separated_list0,
+                sequence::{delimited, separated_pair},
+                IResult,
+            };
+
+            #[derive(Debug, PartialEq)]
+            enum Packet {
+                Value(u8),
+                List(Vec<Packet>),
+            }
+
+            struct PacketPair {
+                left: Packet,
+                right: Packet,
+            }
+
+            $($comparison)*
+
+            impl PacketPair {
+                fn new((left, right): (Packet, Packet)) -> Self {
+                    Self { left, right }
+                }
+
+                fn is_ordered(&self) -> bool {
+                    self.left < self.right
+                }
+            }
+
+            fn packet_pair_list(s: &str) -> IResult<&str, Vec<PacketPair>> {
+                separated_list0(tag("\n\n"), packet_pair)(s)
+            }
+
+            fn packet_pair(s: &str) -> IResult<&str, PacketPair> {
+                map(separated_pair(packet, newline, packet), PacketPair::new)(s)
+            }
+
+            fn element_list(s: &str) -> IResult<&str, Vec<Packet>> {
+                separated_list0(char(','), alt((map(u8, Packet::Value), packet)))(s)
+            }
+
+            fn packet(s: &str) -> IResult<&str, Packet> {
+                map(delimited(char('['), element_list, char(']')), Packet::List)(s)
+            }
+
+            pub fn compute_sum(contents: &str) -> usize {
+                let (_, packet_pairs) = packet_pair_list(contents).unwrap();
+                packet_pairs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, packet_pair)| packet_pair.is_ordered())
+                    .map(|(i, _)| i + 1)
+                    .sum::<usize>()
+            }
+        }
+    };
+}
+
+// Original version
+packet_module!(
+    original,
+    impl PartialOrd for Packet {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            match (self, other) {
+                (Self::Value(l), Self::Value(r)) => l.partial_cmp(r),
+                (Self::List(ls), Self::List(rs)) => ls.partial_cmp(rs),
+                (Self::Value(l), Self::List(rs)) => vec![Self::Value(*l)].partial_cmp(rs),
+                (Self::List(ls), Self::Value(r)) => ls.partial_cmp(&vec![Self::Value(*r)]),
+            }
+        }
+    }
+);
+
+// ikopor@Twitch's version
+packet_module!(
+    ikopor,
+    impl PartialOrd for Packet {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            match (self, other) {
+                (Self::Value(l), Self::Value(r)) => l.partial_cmp(r),
+                (Self::List(ls), Self::List(rs)) => ls.partial_cmp(rs),
+                (Self::Value(l), Self::List(r)) => {
+                    let l: &[Self] = &[Self::Value(*l)];
+                    l.partial_cmp(r)
+                }
+                (Self::List(_), Self::Value(_)) => other.partial_cmp(self).map(Ordering::reverse),
+            }
+        }
+    }
+);
+
+// esitsu@Twitch's version
+packet_module!(
+    esitsu,
+    impl PartialOrd for Packet {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            match (self, other) {
+                (Self::Value(l), Self::Value(r)) => l.partial_cmp(r),
+                (Self::List(l), Self::List(r)) => l.partial_cmp(r),
+                (Self::Value(l), r @ Self::List(_)) => l.partial_cmp(r),
+                (l @ Self::List(_), Self::Value(r)) => l.partial_cmp(r),
+            }
+        }
+    }
+
+    impl PartialEq<Packet> for u8 {
+        fn eq(&self, other: &Packet) -> bool {
+            match other {
+                Packet::Value(val) => self == val,
+                Packet::List(_) => false,
+            }
+        }
+    }
+
+    impl PartialOrd<Packet> for u8 {
+        fn partial_cmp(&self, other: &Packet) -> Option<Ordering> {
+            match other {
+                Packet::Value(val) => self.partial_cmp(val),
+                Packet::List(list) => match &list[..] {
+                    [] => Some(Ordering::Greater),
+                    [item] => self.partial_cmp(item),
+                    [item, ..] => match self.partial_cmp(item) {
+                        Some(Ordering::Equal) => Some(Ordering::Less),
+                        ord => ord,
+                    },
+                },
+            }
+        }
+    }
+
+    impl PartialEq<u8> for Packet {
+        fn eq(&self, other: &u8) -> bool {
+            other == self
+        }
+    }
+
+    impl PartialOrd<u8> for Packet {
+        fn partial_cmp(&self, other: &u8) -> Option<Ordering> {
+            other.partial_cmp(self).map(Ordering::reverse)
+        }
+    }
+);
+
+static INPUT_FILES: &[&str] = &["../inputs/day_13_test.input", "../inputs/day_13.input"];
+
+fn compute_sum_comparison(c: &mut Criterion) {
+    Comparison::new("day_13_part_1")
+        .register("original", original::compute_sum)
+        .register("ikopor", ikopor::compute_sum)
+        .register("esitsu", esitsu::compute_sum)
+        .bench(c, INPUT_FILES);
+}
+
+criterion_group!(day_13_part_1_comparison_bench, compute_sum_comparison);
+criterion_main!(day_13_part_1_comparison_bench);
@@ -3,86 +3,365 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{bail, Context, Result};
-use std::fs;
+use anyhow::{Context, Result};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap},
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    ops::Range,
+};
 
 static INPUT_FILE: &str = "../inputs/day_01.input";
 
+const NUM_TOP_ELVES: usize = 3;
+const HISTOGRAM_BUCKET_SIZE: usize = 5_000;
+
 fn main() -> Result<()> {
-    let big_three: [usize; 3] = process_elves(INPUT_FILE)?;
+    let report = process_elves(INPUT_FILE, NUM_TOP_ELVES, HISTOGRAM_BUCKET_SIZE)?;
+
+    let big_three = report.top_elves();
 
     println!("The big three are {big_three:?}");
 
     let largest = big_three
-        .iter()
-        .max()
-        .context("The array of biggest elves was empty (which should be impossible)")?;
+        .first()
+        .context("The list of biggest elves was empty, so there were no elves")?
+        .calories;
 
-    let sum_of_big_three: usize = big_three.iter().sum();
+    let sum_of_big_three: usize = big_three.iter().map(|elf| elf.calories).sum();
 
     println!("The maximum calories for an elf was {largest}");
     println!("The sum of the big three was {sum_of_big_three}");
+    println!("{report}");
 
     Ok(())
 }
 
-fn process_elves(input_file: &str) -> Result<[usize; 3]> {
-    let contents = fs::read_to_string(input_file)
-        .with_context(|| format!("Failed to open file '{input_file}'"))?;
+fn process_elves(
+    input_file: &str,
+    num_top_elves: usize,
+    bucket_size: usize,
+) -> Result<CalorieReport> {
+    let file =
+        File::open(input_file).with_context(|| format!("Failed to open file '{input_file}'"))?;
 
-    let mut elves = contents
-        .split("\n\n")
-        .map(process_elf)
-        .collect::<Result<Vec<usize>>>()?;
+    Inventories::new(BufReader::new(file)).try_fold(
+        CalorieReport::new(num_top_elves, bucket_size),
+        |mut report, calories| {
+            report.add_elf(calories?);
+            Ok(report)
+        },
+    )
+}
 
-    let num_elves = elves.len();
+/// An elf's position in the input (starting at 0) and the total
+/// calories they're carrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Elf {
+    index: usize,
+    calories: usize,
+}
 
-    if num_elves < 3 {
-        bail!("We had fewer than three elves in the input file");
+// Elves are ordered by their calories, and when two elves have the same
+// calories, the one that comes first in the input counts as "bigger" so
+// that ties in the top-K go to the earlier elf.
+impl Ord for Elf {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.calories
+            .cmp(&other.calories)
+            .then_with(|| other.index.cmp(&self.index))
     }
+}
 
-    let (_, pivot, big) = elves.select_nth_unstable(num_elves - 3);
+impl PartialOrd for Elf {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-    let top_three = [*pivot, big[0], big[1]];
+/**
+ * A summary of the elves' calories that's built up one elf at a time,
+ * so we never need to hold all the elves in memory. We keep the `k`
+ * biggest elves in a min-heap (so the smallest of the big ones is
+ * always the one to kick out), and a histogram of all the elves in
+ * buckets of `bucket_size` calories.
+ *
+ * Because we don't keep every elf, the median is only known to within
+ * a histogram bucket.
+ */
+#[derive(Debug)]
+struct CalorieReport {
+    num_top_elves: usize,
+    top_elves: BinaryHeap<Reverse<Elf>>,
+    num_elves: usize,
+    total_calories: usize,
+    bucket_size: usize,
+    // Maps the start of each bucket to the number of elves in that bucket.
+    histogram: BTreeMap<usize, usize>,
+}
 
-    Ok(top_three)
+impl CalorieReport {
+    fn new(num_top_elves: usize, bucket_size: usize) -> Self {
+        Self {
+            num_top_elves,
+            top_elves: BinaryHeap::with_capacity(num_top_elves + 1),
+            num_elves: 0,
+            total_calories: 0,
+            // A bucket size of 0 would have us dividing by zero.
+            bucket_size: bucket_size.max(1),
+            histogram: BTreeMap::new(),
+        }
+    }
+
+    fn add_elf(&mut self, calories: usize) {
+        let elf = Elf {
+            index: self.num_elves,
+            calories,
+        };
+        self.num_elves += 1;
+        self.total_calories += calories;
+        *self
+            .histogram
+            .entry(calories / self.bucket_size * self.bucket_size)
+            .or_default() += 1;
+
+        self.top_elves.push(Reverse(elf));
+        if self.top_elves.len() > self.num_top_elves {
+            self.top_elves.pop();
+        }
+    }
+
+    /// The (up to) `k` biggest elves, biggest first. If there are
+    /// fewer than `k` elves, this is all of them.
+    fn top_elves(&self) -> Vec<Elf> {
+        let mut top_elves = self
+            .top_elves
+            .iter()
+            .map(|Reverse(elf)| *elf)
+            .collect::<Vec<_>>();
+        top_elves.sort_unstable_by(|a, b| b.cmp(a));
+        top_elves
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn mean(&self) -> Option<f64> {
+        (self.num_elves > 0).then(|| self.total_calories as f64 / self.num_elves as f64)
+    }
+
+    /// An approximate median: the histogram bucket that holds the median
+    /// elf, since we don't keep enough elves to know the exact value.
+    /// When there are an even number of elves we use the lower of the two
+    /// middle elves.
+    fn median_bucket(&self) -> Option<Range<usize>> {
+        let median_position = self.num_elves.checked_sub(1)? / 2;
+        let mut num_seen = 0;
+        self.histogram
+            .iter()
+            .find(|(_, count)| {
+                num_seen += *count;
+                num_seen > median_position
+            })
+            .map(|(start, _)| *start..start + self.bucket_size)
+    }
+
+    fn histogram(&self) -> impl Iterator<Item = (Range<usize>, usize)> + '_ {
+        self.histogram
+            .iter()
+            .map(|(start, count)| (*start..start + self.bucket_size, *count))
+    }
+}
+
+impl Display for CalorieReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Number of elves: {}", self.num_elves)?;
+        if let Some(mean) = self.mean() {
+            writeln!(f, "Mean calories: {mean:.1}")?;
+        }
+        if let Some(median_bucket) = self.median_bucket() {
+            writeln!(
+                f,
+                "Median calories (approximate): somewhere in the {median_bucket:?} bucket"
+            )?;
+        }
+        writeln!(f, "Histogram:")?;
+        for (bucket, count) in self.histogram() {
+            writeln!(
+                f,
+                "{:>15} {count:>4} {}",
+                format!("{bucket:?}"),
+                "#".repeat(count)
+            )?;
+        }
+        Ok(())
+    }
 }
 
 /**
- * An "elf" is a string that is a sequence of numbers, each
- * on their own line. E.g.,
+ * An iterator over the total calories of each elf's inventory, read
+ * from the input one line at a time. Each inventory is a group of
+ * lines with one number per line, e.g.,
  *
  * 10040
  * 9088
  * 11305
- * 5867
- * 10766
- * 9996
- * 11092
  *
- * Our goal is to split that into individual numbers, parse
- * them into `usize` values, and add them up.
+ * and the groups are separated by blank lines.
  */
-fn process_elf(elf_str: &str) -> Result<usize> {
-    elf_str
-        .split_ascii_whitespace()
-        .map(|s| {
-            s.parse::<usize>()
-                .with_context(|| format!("Failed to parse '{s}' to `usize`"))
-        })
-        .sum::<Result<usize>>()
+struct Inventories<R> {
+    lines: Lines<R>,
+    line_number: usize,
+}
+
+impl<R: BufRead> Inventories<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_number: 0,
+        }
+    }
+
+    fn process_elf(&mut self) -> Option<Result<usize>> {
+        let mut calories = None;
+        for line in self.lines.by_ref() {
+            self.line_number += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                // Extra blank lines between (or before) inventories don't
+                // start new, empty elves.
+                if calories.is_some() {
+                    break;
+                }
+                continue;
+            }
+            let line_number = self.line_number;
+            match line.parse::<usize>().with_context(|| {
+                format!("Failed to parse '{line}' on line {line_number} to `usize`")
+            }) {
+                Ok(item) => *calories.get_or_insert(0) += item,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        calories.map(Ok)
+    }
+}
+
+impl<R: BufRead> Iterator for Inventories<R> {
+    type Item = Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.process_elf()
+    }
 }
 
 #[cfg(test)]
 mod process_elves_test {
-    use super::process_elves;
-    use super::INPUT_FILE;
+    use super::*;
+
+    #[test]
+    fn check_process_elves() -> Result<()> {
+        let report = process_elves(INPUT_FILE, NUM_TOP_ELVES, HISTOGRAM_BUCKET_SIZE)?;
+        let big_three = report
+            .top_elves()
+            .iter()
+            .map(|elf| elf.calories)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![74394, 69863, 68579], big_three);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod calorie_report_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "1000
+2000
+3000
+
+4000
+
+5000
+6000
+
+7000
+8000
+9000
+
+10000
+";
+
+    fn example_report(num_top_elves: usize) -> Result<CalorieReport> {
+        Inventories::new(EXAMPLE.as_bytes()).try_fold(
+            CalorieReport::new(num_top_elves, HISTOGRAM_BUCKET_SIZE),
+            |mut report, calories| {
+                report.add_elf(calories?);
+                Ok(report)
+            },
+        )
+    }
+
+    #[test]
+    fn top_elves_with_indices() -> Result<()> {
+        let report = example_report(3)?;
+        assert_eq!(
+            vec![
+                Elf {
+                    index: 3,
+                    calories: 24000
+                },
+                Elf {
+                    index: 2,
+                    calories: 11000
+                },
+                Elf {
+                    index: 4,
+                    calories: 10000
+                },
+            ],
+            report.top_elves()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fewer_elves_than_k() -> Result<()> {
+        let report = example_report(10)?;
+        assert_eq!(5, report.top_elves().len());
+        assert!(example_report(0)?.top_elves().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn statistics() -> Result<()> {
+        let report = example_report(1)?;
+        assert_eq!(5, report.num_elves);
+        assert_eq!(Some(11000.0), report.mean());
+        assert_eq!(Some(10000..15000), report.median_bucket());
+        assert!(report
+            .to_string()
+            .contains("Median calories (approximate): somewhere in the 10000..15000 bucket"));
+        assert_eq!(
+            vec![
+                (0..5000, 1),
+                (5000..10000, 1),
+                (10000..15000, 2),
+                (20000..25000, 1)
+            ],
+            report.histogram().collect::<Vec<_>>()
+        );
+        Ok(())
+    }
 
     #[test]
-    fn check_process_elves() {
-        #[allow(clippy::unwrap_used)]
-        let mut big_three = process_elves(INPUT_FILE).unwrap();
-        big_three.sort_unstable();
-        assert_eq!([68579, 69863, 74394], big_three);
+    fn bad_item() {
+        let mut inventories = Inventories::new(&b"100\n\n1o0\n"[..]);
+        assert!(matches!(inventories.next(), Some(Ok(100))));
+        assert!(matches!(inventories.next(), Some(Err(_))));
     }
 }
@@ -3,98 +3,219 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{bail, Context, Result};
-use std::{fs, str::FromStr};
+use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, SeedableRng};
+use std::{env, fmt::Write, fs, str::FromStr};
 
 static INPUT_FILE: &str = "../inputs/day_02.input";
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-enum Rps {
-    Rock = 1,
-    Paper = 2,
-    Scissors = 3,
-}
+/// A move in a `Game`, which is just its position in the game's cycle
+/// of moves.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+struct Move(usize);
 
-impl FromStr for Rps {
-    type Err = anyhow::Error;
+/// How many points you get for losing, drawing, or winning a round.
+#[derive(Debug, Copy, Clone)]
+struct OutcomeScores {
+    lose: u32,
+    draw: u32,
+    win: u32,
+}
 
-    fn from_str(c: &str) -> Result<Self> {
-        match c {
-            "A" => Ok(Self::Rock),
-            "B" => Ok(Self::Paper),
-            "C" => Ok(Self::Scissors),
-            _ => bail!("Illegal character '{c}' for Rock-Paper-Scissors"),
+impl Default for OutcomeScores {
+    fn default() -> Self {
+        Self {
+            lose: 0,
+            draw: 3,
+            win: 6,
         }
     }
 }
 
-impl TryFrom<i8> for Rps {
-    type Error = anyhow::Error;
+/**
+ * A cyclic hand game with an odd number of moves, where each move beats
+ * the (N-1)/2 moves just before it in the cycle and loses to the (N-1)/2
+ * moves just after it. Rock-Paper-Scissors is the three move version,
+ * and Rock-Paper-Scissors-Lizard-Spock is the five move version (with
+ * the moves in the order Rock, Spock, Paper, Lizard, Scissors).
+ *
+ * Each move has a score (by default 1 for the first move, 2 for the
+ * second, etc.), and a letter for each player in the strategy guide.
+ */
+#[derive(Debug, Clone)]
+struct Game {
+    // Kept separately from `move_names.len()` so that the move arithmetic
+    // can be `const` (a `const` `Vec::len` would need Rust 1.87).
+    num_moves: usize,
+    move_names: Vec<String>,
+    move_scores: Vec<u32>,
+    outcome_scores: OutcomeScores,
+    their_letters: Vec<char>,
+    our_letters: Vec<char>,
+}
 
-    fn try_from(i: i8) -> Result<Self> {
-        match i {
-            1 => Ok(Self::Rock),
-            2 => Ok(Self::Paper),
-            3 => Ok(Self::Scissors),
-            _ => bail!("Can't convert {i} to an RPS value"),
-        }
+impl Game {
+    fn new(move_names: &[&str], their_letters: &str, our_letters: &str) -> Result<Self> {
+        let num_moves = move_names.len();
+        ensure!(
+            num_moves >= 3 && num_moves % 2 == 1,
+            "A cyclic game needs an odd number of moves (at least 3), but we got {num_moves}"
+        );
+        let their_letters = Self::letters(their_letters, num_moves)?;
+        let our_letters = Self::letters(our_letters, num_moves)?;
+        Ok(Self {
+            num_moves,
+            move_names: move_names.iter().map(ToString::to_string).collect(),
+            move_scores: (1..).take(num_moves).collect(),
+            outcome_scores: OutcomeScores::default(),
+            their_letters,
+            our_letters,
+        })
     }
-}
 
-// TODO: Benchmark passing `self` (i.e., an 8-bit value) instead
-//   of `&self` (i.e., a 64-bit value). Does it really make a
-//   difference? Clippy seemed to think so, but it would be interesting
-//   to see what the actual impact is.
-impl Rps {
-    fn game_score(self, their_move: Self) -> u32 {
-        if self.beats() == their_move {
-            6
-        } else if self == their_move {
-            3
-        } else {
-            0
+    fn letters(letters: &str, num_moves: usize) -> Result<Vec<char>> {
+        let letters = letters.chars().collect::<Vec<_>>();
+        ensure!(
+            letters.len() == num_moves,
+            "We need one letter for each of the {num_moves} moves, but got {letters:?}"
+        );
+        ensure!(
+            letters
+                .iter()
+                .enumerate()
+                .all(|(i, c)| !letters[..i].contains(c)),
+            "The letters {letters:?} for the moves must all be different"
+        );
+        Ok(letters)
+    }
+
+    fn rock_paper_scissors() -> Result<Self> {
+        Self::new(&["Rock", "Paper", "Scissors"], "ABC", "XYZ")
+    }
+
+    fn rock_paper_scissors_lizard_spock() -> Result<Self> {
+        Self::new(
+            &["Rock", "Spock", "Paper", "Lizard", "Scissors"],
+            "ABCDE",
+            "VWXYZ",
+        )
+    }
+
+    fn with_move_scores(mut self, move_scores: Vec<u32>) -> Result<Self> {
+        ensure!(
+            move_scores.len() == self.num_moves,
+            "We need a score for each of the moves {:?}, but got {move_scores:?}",
+            self.move_names
+        );
+        self.move_scores = move_scores;
+        Ok(self)
+    }
+
+    const fn with_outcome_scores(mut self, outcome_scores: OutcomeScores) -> Self {
+        self.outcome_scores = outcome_scores;
+        self
+    }
+
+    /// A copy of this game where our letters are assigned to moves by
+    /// `mapping`, i.e., the `i`th of our letters means move `mapping[i]`.
+    fn with_our_mapping(&self, mapping: &[Move]) -> Self {
+        let mut our_letters = self.our_letters.clone();
+        for (letter, m) in self.our_letters.iter().zip(mapping) {
+            our_letters[m.0] = *letter;
         }
+        Self {
+            our_letters,
+            ..self.clone()
+        }
+    }
+
+    fn name(&self, m: Move) -> &str {
+        &self.move_names[m.0]
     }
 
-    fn shift(self, shift_amount: i8) -> Self {
-        let our_val = self as i8;
-        let mut their_val = (3 + our_val + shift_amount) % 3;
-        if their_val == 0 {
-            their_val = 3;
+    const fn num_moves(&self) -> usize {
+        self.num_moves
+    }
+
+    const fn shift(&self, m: Move, shift_amount: isize) -> Move {
+        // The number of moves is small enough that these casts can't
+        // wrap or lose anything.
+        #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+        let position = (m.0 as isize + shift_amount).rem_euclid(self.num_moves() as isize);
+        #[allow(clippy::cast_sign_loss)]
+        Move(position as usize)
+    }
+
+    const fn outcome(&self, our_move: Move, their_move: Move) -> Outcome {
+        let num_moves = self.num_moves();
+        let distance = (num_moves + our_move.0 - their_move.0) % num_moves;
+        if distance == 0 {
+            Outcome::Draw
+        } else if distance <= num_moves / 2 {
+            Outcome::Win
+        } else {
+            Outcome::Lose
         }
-        assert!((1..=3).contains(&their_val));
-        #[allow(clippy::unwrap_used)]
-        their_val.try_into().unwrap()
     }
 
-    fn beats(self) -> Self {
-        self.shift(-1)
+    // When there are more than three moves there's more than one move
+    // that beats (or loses to) any given move; we use the closest one.
+    const fn beats(&self, m: Move) -> Move {
+        self.shift(m, -1)
     }
 
-    fn loses_to(self) -> Self {
-        self.shift(1)
+    const fn loses_to(&self, m: Move) -> Move {
+        self.shift(m, 1)
     }
 
-    // This takes their move (self) and the desired outcome
+    // This takes their move and the desired outcome
     // and returns the move we would need to make to generate
     // that result.
-    fn our_move(self, outcome: Outcome) -> Self {
+    const fn our_move(&self, their_move: Move, outcome: Outcome) -> Move {
         match outcome {
-            Outcome::Draw => self,
-            Outcome::Win => self.loses_to(),
-            Outcome::Lose => self.beats(),
+            Outcome::Draw => their_move,
+            Outcome::Win => self.loses_to(their_move),
+            Outcome::Lose => self.beats(their_move),
         }
     }
+
+    const fn game_score(&self, our_move: Move, their_move: Move) -> u32 {
+        match self.outcome(our_move, their_move) {
+            Outcome::Lose => self.outcome_scores.lose,
+            Outcome::Draw => self.outcome_scores.draw,
+            Outcome::Win => self.outcome_scores.win,
+        }
+    }
+
+    fn score(&self, our_move: Move, their_move: Move) -> u32 {
+        self.game_score(our_move, their_move) + self.move_scores[our_move.0]
+    }
+
+    fn parse_move(letters: &[char], s: &str) -> Result<Move> {
+        letters
+            .iter()
+            .position(|c| s.len() == c.len_utf8() && s.starts_with(*c))
+            .map(Move)
+            .with_context(|| {
+                format!("Illegal character '{s}' for a move; expected one of {letters:?}")
+            })
+    }
+
+    fn their_move(&self, s: &str) -> Result<Move> {
+        Self::parse_move(&self.their_letters, s)
+    }
+
+    fn our_letter_move(&self, s: &str) -> Result<Move> {
+        Self::parse_move(&self.our_letters, s)
+    }
 }
 
-// If we don't derive Copy/Clone, then Clippy recommends passing
-// a reference to `our_move` up above. I'd like to understand
-// that a bit better.
-#[derive(Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 enum Outcome {
-    Lose = 0,
-    Draw = 3,
-    Win = 6,
+    Lose,
+    Draw,
+    Win,
 }
 
 impl FromStr for Outcome {
@@ -110,44 +231,378 @@ impl FromStr for Outcome {
     }
 }
 
+/// The two ways of reading the second column of the strategy guide:
+/// part 1 takes it to be the move we should make, and part 2 takes
+/// it to be the outcome we should aim for.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Reading {
+    Moves,
+    Outcomes,
+}
+
+impl FromStr for Reading {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "moves" => Ok(Self::Moves),
+            "outcomes" => Ok(Self::Outcomes),
+            _ => bail!("Unknown reading '{s}'; expected 'moves' or 'outcomes'"),
+        }
+    }
+}
+
+impl Reading {
+    fn our_move(self, game: &Game, their_move: Move, s: &str) -> Result<Move> {
+        Ok(match self {
+            Self::Moves => game.our_letter_move(s)?,
+            Self::Outcomes => game.our_move(their_move, s.parse()?),
+        })
+    }
+}
+
+static USAGE: &str = "Usage: [--game rps|rpsls] [--reading moves|outcomes] \
+    [--move-scores <n>,<n>,...] [--outcome-scores <lose>,<draw>,<win>] \
+    [--analyze <trials>] [--seed <n>] [--opponent <weight>,<weight>,...]";
+
+const DEFAULT_SEED: u64 = 2022;
+
+/// The settings for `--analyze`, which scores every mapping of our letters
+/// to moves and then plays the guide against a random opponent.
+#[derive(Debug, PartialEq)]
+struct Analysis {
+    num_trials: usize,
+    seed: u64,
+    // How likely the opponent is to play each move; if this is `None`
+    // they play every move equally often.
+    opponent_weights: Option<Vec<f64>>,
+}
+
+struct Options {
+    game: Game,
+    reading: Reading,
+    analysis: Option<Analysis>,
+}
+
+fn parse_scores(scores: &str) -> Result<Vec<u32>> {
+    scores
+        .split(',')
+        .map(|score| {
+            score
+                .parse()
+                .with_context(|| format!("Couldn't parse score '{score}' in '{scores}'"))
+        })
+        .collect()
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut game = Game::rock_paper_scissors()?;
+    let mut reading = Reading::Outcomes;
+    let mut move_scores = None;
+    let mut outcome_scores = OutcomeScores::default();
+    let mut num_trials = None;
+    let mut seed = DEFAULT_SEED;
+    let mut opponent_weights = None;
+    let args = args.collect::<Vec<_>>();
+    for option in args.chunks(2) {
+        match option {
+            [flag, game_name] if flag == "--game" => {
+                game = match game_name.as_str() {
+                    "rps" => Game::rock_paper_scissors()?,
+                    "rpsls" => Game::rock_paper_scissors_lizard_spock()?,
+                    _ => bail!("Unknown game '{game_name}'\n{USAGE}"),
+                }
+            }
+            [flag, r] if flag == "--reading" => reading = r.parse()?,
+            [flag, scores] if flag == "--move-scores" => {
+                move_scores = Some(parse_scores(scores)?);
+            }
+            [flag, scores] if flag == "--outcome-scores" => {
+                let [lose, draw, win] = parse_scores(scores)?[..] else {
+                    bail!("We need exactly three outcome scores, but got '{scores}'");
+                };
+                outcome_scores = OutcomeScores { lose, draw, win };
+            }
+            [flag, trials] if flag == "--analyze" => {
+                num_trials = Some(
+                    trials
+                        .parse()
+                        .with_context(|| format!("Couldn't parse '{trials}' trials"))?,
+                );
+            }
+            [flag, s] if flag == "--seed" => {
+                seed = s
+                    .parse()
+                    .with_context(|| format!("Couldn't parse seed '{s}'"))?;
+            }
+            [flag, weights] if flag == "--opponent" => {
+                opponent_weights = Some(
+                    weights
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<Vec<f64>, _>>()
+                        .with_context(|| format!("Couldn't parse opponent weights '{weights}'"))?,
+                );
+            }
+            _ => bail!("{USAGE}"),
+        }
+    }
+    // The scores have to be applied after we know which game we're
+    // playing, since the game's default move scores depend on the number
+    // of moves.
+    if let Some(move_scores) = move_scores {
+        game = game.with_move_scores(move_scores)?;
+    }
+    ensure!(
+        num_trials.is_some() || (opponent_weights.is_none() && seed == DEFAULT_SEED),
+        "--seed and --opponent only make sense with --analyze\n{USAGE}"
+    );
+    let analysis = num_trials.map(|num_trials| Analysis {
+        num_trials,
+        seed,
+        opponent_weights,
+    });
+    Ok(Options {
+        game: game.with_outcome_scores(outcome_scores),
+        reading,
+        analysis,
+    })
+}
+
 fn main() -> Result<()> {
+    let Options {
+        game,
+        reading,
+        analysis,
+    } = parse_args(env::args().skip(1))?;
+
     let contents = fs::read_to_string(INPUT_FILE)
         .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
 
-    let total_score = contents.lines().map(process_game).sum::<Result<u32>>()?;
+    if let Some(analysis) = analysis {
+        return analyze(&game, reading, &contents, &analysis);
+    }
+
+    let total_score = contents
+        .lines()
+        .map(|line| process_game(&game, reading, line))
+        .sum::<Result<u32>>()?;
 
     println!("The total score was {total_score}");
 
     Ok(())
 }
 
-fn process_game(line: &str) -> Result<u32> {
+fn process_game(game: &Game, reading: Reading, line: &str) -> Result<u32> {
+    let (their_move, our_move) = plan_game(game, reading, line)?;
+    Ok(game.score(our_move, their_move))
+}
+
+// The moves the guide says the two players will make, i.e., their move
+// and the move we'll make in response.
+fn plan_game(game: &Game, reading: Reading, line: &str) -> Result<(Move, Move)> {
     let mut parts = line.split_ascii_whitespace();
 
-    let their_move = parts
-        .next()
-        .with_context(|| format!("Missing first move on line '{line}'"))?
-        .parse::<Rps>()?;
+    let their_move = game.their_move(
+        parts
+            .next()
+            .with_context(|| format!("Missing first move on line '{line}'"))?,
+    )?;
+
+    let our_move = reading.our_move(
+        game,
+        their_move,
+        parts
+            .next()
+            .with_context(|| format!("Missing second column on line '{line}'"))?,
+    )?;
+
+    Ok((their_move, our_move))
+}
+
+/// The total score for the guide in `contents` for every way of mapping
+/// our letters to moves (using the part 1 reading), in the order that
+/// `Itertools::permutations` generates the mappings.
+fn mapping_scores(game: &Game, contents: &str) -> Result<Vec<(Vec<Move>, u32)>> {
+    (0..game.num_moves())
+        .map(Move)
+        .permutations(game.num_moves())
+        .map(|mapping| {
+            let mapped_game = game.with_our_mapping(&mapping);
+            let score = contents
+                .lines()
+                .map(|line| process_game(&mapped_game, Reading::Moves, line))
+                .sum::<Result<u32>>()?;
+            Ok((mapping, score))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct Simulation {
+    mean: f64,
+    variance: f64,
+}
+
+/// Play the guide's moves `num_trials` times against an opponent who
+/// ignores the guide and picks each move at random using `weights`.
+fn simulate(
+    game: &Game,
+    plan: &[(Move, Move)],
+    weights: &[f64],
+    num_trials: usize,
+    seed: u64,
+) -> Result<Simulation> {
+    ensure!(num_trials > 0, "We need at least one trial to simulate");
+    let opponent = WeightedIndex::new(weights)
+        .with_context(|| format!("Illegal opponent weights {weights:?}"))?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let totals = (0..num_trials)
+        .map(|_| {
+            plan.iter()
+                .map(|(_, our_move)| game.score(*our_move, Move(opponent.sample(&mut rng))))
+                .sum::<u32>()
+        })
+        .map(f64::from)
+        .collect::<Vec<_>>();
+    #[allow(clippy::cast_precision_loss)]
+    let num_trials = num_trials as f64;
+    let mean = totals.iter().sum::<f64>() / num_trials;
+    let variance = totals.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / num_trials;
+    Ok(Simulation { mean, variance })
+}
+
+/// The exact expected score of `plan` against the same random opponent
+/// as in `simulate`, to check the simulation against.
+fn expected_score(game: &Game, plan: &[(Move, Move)], weights: &[f64]) -> f64 {
+    let total_weight = weights.iter().sum::<f64>();
+    plan.iter()
+        .map(|(_, our_move)| {
+            weights
+                .iter()
+                .enumerate()
+                .map(|(m, w)| w * f64::from(game.score(*our_move, Move(m))))
+                .sum::<f64>()
+                / total_weight
+        })
+        .sum()
+}
 
-    let outcome = parts
-        .next()
-        .with_context(|| format!("Missing outcome on line '{line}'"))?
-        .parse::<Outcome>()?;
+fn describe_mapping(game: &Game, mapping: &[Move]) -> String {
+    game.our_letters
+        .iter()
+        .zip(mapping)
+        .map(|(letter, m)| format!("{letter}={}", game.name(*m)))
+        .join(" ")
+}
 
-    let our_move = their_move.our_move(outcome);
+fn analyze(game: &Game, reading: Reading, contents: &str, analysis: &Analysis) -> Result<()> {
+    let mut report = String::new();
+    let scores = mapping_scores(game, contents)?;
+    writeln!(report, "Scores for each mapping of our letters to moves:")?;
+    for (mapping, score) in &scores {
+        writeln!(report, "  {}: {score}", describe_mapping(game, mapping))?;
+    }
+    let (best, best_score) = scores
+        .iter()
+        .max_by_key(|(_, score)| score)
+        .context("There were no mappings")?;
+    let (worst, worst_score) = scores
+        .iter()
+        .min_by_key(|(_, score)| score)
+        .context("There were no mappings")?;
+    writeln!(
+        report,
+        "Best: {} ({best_score})",
+        describe_mapping(game, best)
+    )?;
+    writeln!(
+        report,
+        "Worst: {} ({worst_score})",
+        describe_mapping(game, worst)
+    )?;
 
-    Ok(our_move.game_score(their_move) + (our_move as u32))
+    let plan = contents
+        .lines()
+        .map(|line| plan_game(game, reading, line))
+        .collect::<Result<Vec<_>>>()?;
+    let weights = analysis
+        .opponent_weights
+        .clone()
+        .unwrap_or_else(|| vec![1.0; game.num_moves()]);
+    ensure!(
+        weights.len() == game.num_moves(),
+        "We need an opponent weight for each of the {} moves, but got {weights:?}",
+        game.num_moves()
+    );
+    let simulation = simulate(game, &plan, &weights, analysis.num_trials, analysis.seed)?;
+    writeln!(
+        report,
+        "Against a random opponent with weights {weights:?} ({} trials, seed {}):",
+        analysis.num_trials, analysis.seed
+    )?;
+    writeln!(
+        report,
+        "  mean {:.1}, variance {:.1} (exact expected value {:.1})",
+        simulation.mean,
+        simulation.variance,
+        expected_score(game, &plan, &weights)
+    )?;
+    print!("{report}");
+    Ok(())
 }
 
 #[cfg(test)]
 mod beats_tests {
     use super::*;
 
+    const ROCK: Move = Move(0);
+    const PAPER: Move = Move(1);
+    const SCISSORS: Move = Move(2);
+
+    #[test]
+    fn beats_check() -> Result<()> {
+        let game = Game::rock_paper_scissors()?;
+        assert_eq!(game.beats(ROCK), SCISSORS);
+        assert_eq!(game.beats(PAPER), ROCK);
+        assert_eq!(game.beats(SCISSORS), PAPER);
+        Ok(())
+    }
+
     #[test]
-    fn beats_check() {
-        assert_eq!(Rps::Rock.beats(), Rps::Scissors);
-        assert_eq!(Rps::Paper.beats(), Rps::Rock);
-        assert_eq!(Rps::Scissors.beats(), Rps::Paper);
+    fn lizard_spock() -> Result<()> {
+        let game = Game::rock_paper_scissors_lizard_spock()?;
+        let named = |name| {
+            game.move_names
+                .iter()
+                .position(|n| n == name)
+                .map(Move)
+                .context("Unknown move")
+        };
+        for (winner, loser) in [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ] {
+            let (winner, loser) = (named(winner)?, named(loser)?);
+            assert_eq!(Outcome::Win, game.outcome(winner, loser));
+            assert_eq!(Outcome::Lose, game.outcome(loser, winner));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn even_number_of_moves() {
+        assert!(Game::new(&["Rock", "Paper"], "AB", "XY").is_err());
+        assert!(Game::new(&["Rock", "Paper", "Scissors"], "AB", "XYZ").is_err());
+        assert!(Game::new(&["Rock", "Paper", "Scissors"], "AAB", "XYZ").is_err());
     }
 }
 
@@ -155,10 +610,102 @@ mod beats_tests {
 mod score_tests {
     use super::*;
 
+    const ROCK: Move = Move(0);
+    const PAPER: Move = Move(1);
+    const SCISSORS: Move = Move(2);
+
+    static EXAMPLE: &str = "A Y\nB X\nC Z";
+
+    fn total_score(game: &Game, reading: Reading) -> Result<u32> {
+        EXAMPLE
+            .lines()
+            .map(|line| process_game(game, reading, line))
+            .sum()
+    }
+
+    #[test]
+    fn we_play_rock() -> Result<()> {
+        let game = Game::rock_paper_scissors()?;
+        assert_eq!(3, game.game_score(ROCK, ROCK));
+        assert_eq!(0, game.game_score(ROCK, PAPER));
+        assert_eq!(6, game.game_score(ROCK, SCISSORS));
+        Ok(())
+    }
+
+    #[test]
+    fn both_readings() -> Result<()> {
+        let game = Game::rock_paper_scissors()?;
+        assert_eq!(15, total_score(&game, Reading::Moves)?);
+        assert_eq!(12, total_score(&game, Reading::Outcomes)?);
+        Ok(())
+    }
+
+    #[test]
+    fn custom_scores() -> Result<()> {
+        let game = Game::rock_paper_scissors()?
+            .with_move_scores(vec![10, 20, 30])?
+            .with_outcome_scores(OutcomeScores {
+                lose: 1,
+                draw: 2,
+                win: 3,
+            });
+        // Paper beats Rock (23), Rock loses to Paper (11), and Scissors
+        // draws with Scissors (32).
+        assert_eq!(66, total_score(&game, Reading::Moves)?);
+        assert!(game.with_move_scores(vec![1, 2]).is_err());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "A Y\nB X\nC Z";
+
+    #[test]
+    fn all_mappings() -> Result<()> {
+        let game = Game::rock_paper_scissors()?;
+        let scores = mapping_scores(&game, EXAMPLE)?;
+        assert_eq!(6, scores.len());
+        // The identity mapping is the part 1 reading.
+        assert_eq!((vec![Move(0), Move(1), Move(2)], 15), scores[0]);
+        // Always playing what beats their move is the best you can do.
+        assert_eq!(Some(24), scores.iter().map(|(_, score)| *score).max());
+        Ok(())
+    }
+
+    #[test]
+    fn simulation_matches_expectation() -> Result<()> {
+        let game = Game::rock_paper_scissors()?;
+        let plan = EXAMPLE
+            .lines()
+            .map(|line| plan_game(&game, Reading::Moves, line))
+            .collect::<Result<Vec<_>>>()?;
+        let weights = [1.0, 2.0, 1.0];
+        let simulation = simulate(&game, &plan, &weights, 10_000, DEFAULT_SEED)?;
+        let expected = expected_score(&game, &plan, &weights);
+        assert!((simulation.mean - expected).abs() < 0.5);
+        assert!(simulation.variance > 0.0);
+        // Runs with the same seed give the same results.
+        let again = simulate(&game, &plan, &weights, 10_000, DEFAULT_SEED)?;
+        assert_eq!(simulation.mean.to_bits(), again.mean.to_bits());
+        Ok(())
+    }
+
     #[test]
-    fn we_play_rock() {
-        assert_eq!(3, Rps::Rock.game_score(Rps::Rock));
-        assert_eq!(0, Rps::Rock.game_score(Rps::Paper));
-        assert_eq!(6, Rps::Rock.game_score(Rps::Scissors));
+    fn analysis_args() -> Result<()> {
+        let args = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let options = parse_args(args(&["--analyze", "100", "--opponent", "1,2,3"]).into_iter())?;
+        assert_eq!(
+            Some(Analysis {
+                num_trials: 100,
+                seed: DEFAULT_SEED,
+                opponent_weights: Some(vec![1.0, 2.0, 3.0])
+            }),
+            options.analysis
+        );
+        assert!(parse_args(args(&["--seed", "7"]).into_iter()).is_err());
+        Ok(())
     }
 }
@@ -3,51 +3,237 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{bail, Context, Result};
-use itertools::{Chunk, Itertools};
-use std::{collections::HashSet, fs, str::Lines};
+use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
+use std::{env, fs};
 
 static INPUT_FILE: &str = "../inputs/day_03.input";
 
+static STANDARD_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
 fn main() -> Result<()> {
-    let sum_of_priorities = process_groups(INPUT_FILE)?;
+    let (alphabet, grouping) = parse_args(env::args().skip(1))?;
+
+    let sum_of_priorities = process_groups(INPUT_FILE, &alphabet, grouping)?;
 
     println!("The sum of the priorities is {sum_of_priorities:?}");
 
     Ok(())
 }
 
-fn process_groups(input_file: &str) -> Result<u32> {
+static USAGE: &str = "Usage: [--group-size <n> | --compartments] [--alphabet <items>]";
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<(Alphabet, Grouping)> {
+    let mut alphabet = Alphabet::new(STANDARD_ALPHABET)?;
+    let mut grouping = Grouping::Groups(3);
+    let mut args = args;
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--compartments" => grouping = Grouping::Compartments,
+            "--group-size" => {
+                let size = args.next().context(USAGE)?;
+                let size = size
+                    .parse()
+                    .with_context(|| format!("Couldn't parse group size '{size}'"))?;
+                ensure!(size > 0, "Groups must have at least one rucksack");
+                grouping = Grouping::Groups(size);
+            }
+            "--alphabet" => alphabet = Alphabet::new(&args.next().context(USAGE)?)?,
+            _ => bail!("Unknown option '{flag}'\n{USAGE}"),
+        }
+    }
+    Ok((alphabet, grouping))
+}
+
+/// How we find the rucksacks whose shared item we want: part 1 splits
+/// each line into two compartments, and part 2 takes groups of lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    Compartments,
+    Groups(usize),
+}
+
+/// A set of items, with one bit for each of the (at most 64) item types
+/// in an `Alphabet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Items(u64);
+
+impl Items {
+    const ALL: Self = Self(u64::MAX);
+
+    const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    // The positions in the alphabet of the items in this set.
+    fn positions(self) -> impl Iterator<Item = usize> {
+        (0..u64::BITS as usize).filter(move |i| self.0 & (1 << i) != 0)
+    }
+}
+
+/// The item types, in order of priority; the first item has priority 1,
+/// the second priority 2, and so on.
+#[derive(Debug)]
+struct Alphabet {
+    items: Vec<char>,
+}
+
+impl Alphabet {
+    fn new(items: &str) -> Result<Self> {
+        let items = items.chars().collect::<Vec<_>>();
+        ensure!(
+            items.len() <= u64::BITS as usize,
+            "An alphabet can have at most {} items, but '{}' has {}",
+            u64::BITS,
+            items.iter().collect::<String>(),
+            items.len()
+        );
+        if let Some(duplicate) = items.iter().duplicates().next() {
+            bail!("The item '{duplicate}' appears more than once in the alphabet");
+        }
+        Ok(Self { items })
+    }
+
+    fn items(&self, rucksack: &str) -> Result<Items> {
+        rucksack.chars().try_fold(Items(0), |items, c| {
+            let position = self
+                .items
+                .iter()
+                .position(|&item| item == c)
+                .with_context(|| format!("Illegal item '{c}' in '{rucksack}'"))?;
+            Ok(Items(items.0 | 1 << position))
+        })
+    }
+
+    fn describe(&self, items: Items) -> String {
+        items.positions().map(|i| self.items[i]).collect()
+    }
+
+    // `source` describes where the rucksacks came from for the error
+    // messages, e.g., "the group starting on line 4".
+    fn shared_priority(&self, rucksacks: &[&str], source: &str) -> Result<u32> {
+        let shared = rucksacks
+            .iter()
+            .map(|rucksack| self.items(rucksack))
+            .fold_ok(Items::ALL, Items::intersection)
+            .with_context(|| format!("Failed to read the rucksacks in {source}"))?;
+        match shared.positions().collect::<Vec<_>>()[..] {
+            [position] => {
+                // The alphabet has at most 64 items, so this can't truncate.
+                #[allow(clippy::cast_possible_truncation)]
+                Ok(position as u32 + 1)
+            }
+            [] => bail!("There was no shared item in {source}: {rucksacks:?}"),
+            _ => bail!(
+                "There were multiple shared items '{}' in {source}: {rucksacks:?}",
+                self.describe(shared)
+            ),
+        }
+    }
+}
+
+fn process_groups(input_file: &str, alphabet: &Alphabet, grouping: Grouping) -> Result<u32> {
     let contents = fs::read_to_string(input_file)
         .with_context(|| format!("Failed to open file '{input_file}'"))?;
 
-    contents
-        .lines()
-        .chunks(3)
-        .into_iter()
-        .map(process_group)
-        .sum()
+    sum_of_priorities(&contents, alphabet, grouping)
 }
 
-fn process_group(group: Chunk<Lines>) -> Result<u32> {
-    let shared_items = group
-        .map(|s: &str| s.chars().collect::<HashSet<_>>())
-        .reduce(|so_far, other| so_far.intersection(&other).copied().collect())
-        .with_context(|| "There were no lines in a group")?;
+fn sum_of_priorities(contents: &str, alphabet: &Alphabet, grouping: Grouping) -> Result<u32> {
+    let lines = contents.lines().collect::<Vec<_>>();
+    match grouping {
+        Grouping::Compartments => lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| process_compartments(alphabet, line, i + 1))
+            .sum(),
+        Grouping::Groups(group_size) => lines
+            .chunks(group_size)
+            .enumerate()
+            .map(|(i, group)| process_group(alphabet, group, i * group_size + 1, group_size))
+            .sum(),
+    }
+}
 
-    let shared_item = shared_items
-        .iter()
-        .next()
-        .with_context(|| "There was no shared item in a group")?;
-    let shared_item = *shared_item;
+// `is_multiple_of` would need Rust 1.87.
+#[allow(clippy::manual_is_multiple_of)]
+fn process_compartments(alphabet: &Alphabet, line: &str, line_number: usize) -> Result<u32> {
+    let num_chars = line.chars().count();
+    ensure!(
+        num_chars % 2 == 0,
+        "The rucksack '{line}' on line {line_number} has an odd number of items, so it can't be split into compartments"
+    );
+    let middle = line.char_indices().nth(num_chars / 2).map_or(0, |(i, _)| i);
+    let compartments: [&str; 2] = line.split_at(middle).into();
+    alphabet.shared_priority(
+        &compartments,
+        &format!("the compartments on line {line_number}"),
+    )
+}
 
-    Ok(match shared_item {
-        'a'..='z' => char_to_priority(shared_item, 'a', 1),
-        'A'..='Z' => char_to_priority(shared_item, 'A', 27),
-        _ => bail!("Illegal shared item '{}'", shared_item),
-    })
+fn process_group(
+    alphabet: &Alphabet,
+    group: &[&str],
+    first_line: usize,
+    group_size: usize,
+) -> Result<u32> {
+    ensure!(
+        group.len() == group_size,
+        "The group starting on line {first_line} only has {} of its {group_size} rucksacks",
+        group.len()
+    );
+    alphabet.shared_priority(group, &format!("the group starting on line {first_line}"))
 }
 
-const fn char_to_priority(c: char, offset_char: char, offset_val: u32) -> u32 {
-    c as u32 - (offset_char as u32) + offset_val
+#[cfg(test)]
+mod rucksack_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw";
+
+    #[test]
+    fn both_groupings() -> Result<()> {
+        let alphabet = Alphabet::new(STANDARD_ALPHABET)?;
+        assert_eq!(
+            157,
+            sum_of_priorities(EXAMPLE, &alphabet, Grouping::Compartments)?
+        );
+        assert_eq!(
+            70,
+            sum_of_priorities(EXAMPLE, &alphabet, Grouping::Groups(3))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn custom_alphabet() -> Result<()> {
+        let alphabet = Alphabet::new("zyx")?;
+        assert_eq!(
+            1,
+            sum_of_priorities("zxz\nzyy", &alphabet, Grouping::Groups(2))?
+        );
+        assert!(Alphabet::new("abca").is_err());
+        assert!(Alphabet::new(&"a".repeat(65)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bad_groups() -> Result<()> {
+        let alphabet = Alphabet::new(STANDARD_ALPHABET)?;
+        let error = |contents, grouping| {
+            sum_of_priorities(contents, &alphabet, grouping)
+                .map_or_else(|e| format!("{e:#}"), |_| String::new())
+        };
+        assert!(error("ab\ncd", Grouping::Groups(2)).contains("no shared item"));
+        assert!(error("abc\nabd", Grouping::Groups(2)).contains("multiple shared items 'ab'"));
+        assert!(error("aa\naa\naa\na", Grouping::Groups(3)).contains("only has 1 of its 3"));
+        assert!(error("aab", Grouping::Compartments).contains("odd number"));
+        assert!(error("a!\na!", Grouping::Groups(2)).contains("Illegal item '!'"));
+        Ok(())
+    }
 }
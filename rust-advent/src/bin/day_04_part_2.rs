@@ -3,31 +3,75 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{Context, Result};
-use std::{fs, str::FromStr};
+use anyhow::{ensure, Context, Result};
+use std::{fmt::Display, fs, str::FromStr};
 
 static INPUT_FILE: &str = "../inputs/day_04.input";
 
 fn main() -> Result<()> {
-    let num_overlapping_assignments = process_pairs(INPUT_FILE)?;
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+    let lines = parse_lines(&contents)?;
+
+    let num_overlapping_assignments = count_overlapping(&lines);
 
     println!("The number of overlapping assignments is {num_overlapping_assignments:?}");
 
+    let redundant_elves = redundant_elves(&lines);
+    println!("There are {} fully redundant elves:", redundant_elves.len());
+    for RedundantElf {
+        line_number,
+        elf_number,
+    } in redundant_elves
+    {
+        println!("  elf {elf_number} on line {line_number}");
+    }
+
+    println!(
+        "There are {} uncovered sections in total",
+        total_uncovered(&lines)
+    );
+
+    let graph = overlap_graph(&lines);
+    let num_edges = graph.iter().map(Vec::len).sum::<usize>() / 2;
+    println!("The overlap graph between lines has {num_edges} edges");
+    if let Some((line, neighbors)) = graph.iter().enumerate().max_by_key(|(_, n)| n.len()) {
+        println!(
+            "Line {} ({}) overlaps the most other lines ({})",
+            line + 1,
+            lines[line]
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+            neighbors.len()
+        );
+    }
+
     Ok(())
 }
 
-fn process_pairs(input_file: &str) -> Result<usize> {
-    let contents = fs::read_to_string(input_file)
-        .with_context(|| format!("Failed to open file '{input_file}'"))?;
+fn parse_lines(contents: &str) -> Result<Vec<Vec<SectionAssignment>>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            parse_line(line).with_context(|| format!("Failed to parse line {}", i + 1))
+        })
+        .collect()
+}
 
-    contents.lines().try_fold(0, |current_count, line| {
-        // Thanks to @NathanielBumppo@Twitch for pointing out
-        // that we can use `usize::from()` to convert from a boolean
-        // to a `usize` in exactly the desired way.
-        Ok(current_count + usize::from(partially_overlaps(line)?))
-    })
+// A line can have any number of assignments, separated by commas.
+fn parse_line(line: &str) -> Result<Vec<SectionAssignment>> {
+    line.split(',').map(str::parse).collect()
+}
+
+fn count_overlapping(lines: &[Vec<SectionAssignment>]) -> usize {
+    lines.iter().filter(|line| partially_overlaps(line)).count()
 }
 
+/// An inclusive range of sections, e.g., `2-4` is the sections 2, 3, and 4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct SectionAssignment {
     start: usize,
     end: usize,
@@ -56,27 +100,227 @@ impl FromStr for SectionAssignment {
             .parse()
             .with_context(|| format!("Couldn't parse '{end}' to an int"))?;
 
+        ensure!(start <= end, "The range '{s}' ends before it starts");
+
         Ok(Self { start, end })
     }
 }
 
+impl Display for SectionAssignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
 impl SectionAssignment {
-    const fn contains_endpoint_of(&self, other: &Self) -> bool {
-        self.contains(other.start) || self.contains(other.end)
+    const fn len(&self) -> usize {
+        self.end - self.start + 1
     }
 
     const fn contains(&self, section: usize) -> bool {
         self.start <= section && section <= self.end
     }
+
+    const fn fully_contains(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    const fn overlaps(&self, other: &Self) -> bool {
+        self.contains(other.start) || other.contains(self.start)
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        self.overlaps(other).then(|| Self {
+            start: self.start.max(other.start),
+            end: self.end.min(other.end),
+        })
+    }
+
+    /// The union of two assignments is only a single assignment if they
+    /// overlap or are right next to each other (like `2-3` and `4-5`).
+    fn union(&self, other: &Self) -> Option<Self> {
+        (self.gap(other) == 0).then(|| Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        })
+    }
+
+    /// The number of sections strictly between two assignments, which is
+    /// zero if they overlap or are adjacent.
+    const fn gap(&self, other: &Self) -> usize {
+        if self.end < other.start {
+            other.start - self.end - 1
+        } else if other.end < self.start {
+            self.start - other.end - 1
+        } else {
+            0
+        }
+    }
 }
 
-fn partially_overlaps(line: &str) -> Result<bool> {
-    let (first, second) = line
-        .split_once(',')
-        .with_context(|| format!("There was no comma in the line '{line}'"))?;
+fn partially_overlaps(assignments: &[SectionAssignment]) -> bool {
+    assignments.iter().enumerate().any(|(i, first)| {
+        assignments[i + 1..]
+            .iter()
+            .any(|second| first.overlaps(second))
+    })
+}
+
+/// An elf identified by its line in the input and its position in that
+/// line, both counting from 1.
+#[derive(Debug, PartialEq, Eq)]
+struct RedundantElf {
+    line_number: usize,
+    elf_number: usize,
+}
+
+/// The elves whose assignment is completely covered by another elf on the
+/// same line. If several elves have identical assignments, all but the
+/// first count as redundant.
+fn redundant_elves(lines: &[Vec<SectionAssignment>]) -> Vec<RedundantElf> {
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(line_index, assignments)| {
+            assignments
+                .iter()
+                .enumerate()
+                .filter(|(i, assignment)| {
+                    assignments.iter().enumerate().any(|(j, other)| {
+                        j != *i
+                            && other.fully_contains(assignment)
+                            && (other != *assignment || j < *i)
+                    })
+                })
+                .map(move |(i, _)| RedundantElf {
+                    line_number: line_index + 1,
+                    elf_number: i + 1,
+                })
+        })
+        .collect()
+}
 
-    let first: SectionAssignment = first.parse()?;
-    let second: SectionAssignment = second.parse()?;
+/// The sections covered by a line's assignments, as a sorted list of
+/// non-overlapping, non-adjacent assignments.
+fn coverage(assignments: &[SectionAssignment]) -> Vec<SectionAssignment> {
+    let mut sorted = assignments.to_vec();
+    sorted.sort_unstable();
+    let mut merged: Vec<SectionAssignment> = Vec::new();
+    for assignment in sorted {
+        let union = merged.last().and_then(|last| last.union(&assignment));
+        match (union, merged.last_mut()) {
+            (Some(union), Some(last)) => *last = union,
+            _ => merged.push(assignment),
+        }
+    }
+    merged
+}
+
+/// The number of sections between the first and last sections assigned
+/// on each line that no elf on that line is assigned, summed over all
+/// the lines.
+fn total_uncovered(lines: &[Vec<SectionAssignment>]) -> usize {
+    lines
+        .iter()
+        .map(|assignments| {
+            let coverage = coverage(assignments);
+            let (Some(first), Some(last)) = (coverage.first(), coverage.last()) else {
+                return 0;
+            };
+            let span = SectionAssignment {
+                start: first.start,
+                end: last.end,
+            };
+            span.len() - coverage.iter().map(SectionAssignment::len).sum::<usize>()
+        })
+        .sum()
+}
+
+/// For each line, the other lines (by index) whose assignments overlap
+/// at least one of its assignments.
+fn overlap_graph(lines: &[Vec<SectionAssignment>]) -> Vec<Vec<usize>> {
+    let coverages = lines.iter().map(|line| coverage(line)).collect::<Vec<_>>();
+    coverages
+        .iter()
+        .enumerate()
+        .map(|(i, coverage)| {
+            coverages
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| {
+                    i != *j
+                        && coverage
+                            .iter()
+                            .any(|a| other.iter().any(|b| a.intersection(b).is_some()))
+                })
+                .map(|(j, _)| j)
+                .collect()
+        })
+        .collect()
+}
 
-    Ok(first.contains_endpoint_of(&second) || second.contains_endpoint_of(&first))
+#[cfg(test)]
+mod section_assignment_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+
+    fn assignment(start: usize, end: usize) -> SectionAssignment {
+        SectionAssignment { start, end }
+    }
+
+    const fn elf(line_number: usize, elf_number: usize) -> RedundantElf {
+        RedundantElf {
+            line_number,
+            elf_number,
+        }
+    }
+
+    #[test]
+    fn interval_operations() -> Result<()> {
+        let first: SectionAssignment = "2-6".parse()?;
+        let second: SectionAssignment = "4-8".parse()?;
+        assert_eq!(5, first.len());
+        assert_eq!(Some(assignment(4, 6)), first.intersection(&second));
+        assert_eq!(Some(assignment(2, 8)), first.union(&second));
+        assert_eq!(
+            Some(assignment(2, 5)),
+            assignment(2, 3).union(&assignment(4, 5))
+        );
+        assert_eq!(None, assignment(2, 3).union(&assignment(5, 5)));
+        assert_eq!(None, assignment(2, 3).intersection(&assignment(4, 5)));
+        assert_eq!(3, assignment(2, 3).gap(&assignment(7, 9)));
+        assert_eq!(0, first.gap(&second));
+        assert!(assignment(2, 8).fully_contains(&assignment(3, 7)));
+        assert!(!first.fully_contains(&second));
+        assert!("5-3".parse::<SectionAssignment>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reports() -> Result<()> {
+        let lines = parse_lines(EXAMPLE)?;
+        assert_eq!(4, count_overlapping(&lines));
+        assert_eq!(vec![elf(4, 2), elf(5, 1)], redundant_elves(&lines));
+        assert_eq!(1, total_uncovered(&lines));
+        let graph = overlap_graph(&lines);
+        assert_eq!(vec![1, 2, 3, 4, 5], graph[0]);
+        assert_eq!(vec![0, 2, 3, 4, 5], graph[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn many_assignments_per_line() -> Result<()> {
+        let lines = parse_lines("1-2,4-4,9-9,3-5")?;
+        assert_eq!(1, count_overlapping(&lines));
+        assert_eq!(vec![elf(1, 2)], redundant_elves(&lines));
+        assert_eq!(3, total_uncovered(&lines));
+        assert_eq!(vec![elf(1, 2)], redundant_elves(&parse_lines("3-4,3-4")?));
+        Ok(())
+    }
 }
@@ -3,16 +3,14 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{ensure, Context, Result};
-use std::{fs, str::FromStr};
+use anyhow::{bail, ensure, Context, Result};
+use std::{env, fmt::Display, fs, str::FromStr};
 
 static INPUT_FILE: &str = "../inputs/day_05.input";
 
-const NUM_STACKS: usize = 9;
-
-#[derive(Default, Debug)]
+#[derive(Debug)]
 struct Stacks {
-    stacks: [Vec<char>; NUM_STACKS],
+    stacks: Vec<Vec<char>>,
 }
 
 // An alternative approach would be to just do `line.chars()` and
@@ -20,31 +18,54 @@ struct Stacks {
 // creating the `Vec<char>` for `line` that we have at the moment.
 // We'd probably have to special case the first stack, though, and
 // that would be ugly, so I'm not sure that wins.
-fn extract_stack_elements(line: &str) -> Vec<char> {
+//
+// Lines can be shorter than the full width of the drawing if the
+// stacks on the right don't reach that high, so anything past the end
+// of the line is treated as a space.
+fn extract_stack_elements(line: &str, num_stacks: usize) -> Vec<char> {
     let line = line.chars().collect::<Vec<_>>();
-    (0..NUM_STACKS).map(|pos| line[1 + 4 * pos]).collect()
+    (0..num_stacks)
+        .map(|pos| line.get(1 + 4 * pos).copied().unwrap_or(' '))
+        .collect()
+}
+
+// The last line of the drawing numbers the stacks, e.g., ` 1   2   3 `,
+// which tells us how many stacks there are.
+fn count_stacks(label_line: &str) -> Result<usize> {
+    let labels = label_line.split_ascii_whitespace().collect::<Vec<_>>();
+    for (i, label) in labels.iter().enumerate() {
+        ensure!(
+            label.parse::<usize>().ok() == Some(i + 1),
+            "Expected stack label {} but found '{label}' in '{label_line}'",
+            i + 1
+        );
+    }
+    ensure!(
+        !labels.is_empty(),
+        "There were no stack labels in '{label_line}'"
+    );
+    Ok(labels.len())
 }
 
 impl FromStr for Stacks {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        let stacks = s
-            .lines()
-            // We reverse the lines because we want the "bottom" lines
-            // to be pushed onto the stacks first so those values end
-            // up on the bottom of the stacks.
-            .rev()
-            // We'll just skip the line with the stack numbers since we
-            // never use them.
-            .skip(1)
-            // Convert each line to a `Vec<char>` that holds the 9 elements
+        // We reverse the lines because we want the "bottom" lines
+        // to be pushed onto the stacks first so those values end
+        // up on the bottom of the stacks.
+        let mut lines = s.lines().rev();
+        // The line with the stack numbers is now first, and is only
+        // used to work out how many stacks there are.
+        let num_stacks = count_stacks(lines.next().context("The stack drawing was empty")?)?;
+        let stacks = lines
+            // Convert each line to a `Vec<char>` that holds the elements
             // at each level. We'll put spaces in that `Vec<char>` for stacks
             // that don't have anything at that level.
-            .map(extract_stack_elements)
+            .map(|line| extract_stack_elements(line, num_stacks))
             // "Loop" over each line/level, pushing the non-space values onto
             // the appropriate stacks.
-            .fold(Self::default(), |stacks, line| {
+            .fold(Self::new(num_stacks), |stacks, line| {
                 stacks.push_values_on_stacks(&line)
             });
 
@@ -52,7 +73,47 @@ impl FromStr for Stacks {
     }
 }
 
+/// Draw the stacks the same way as the puzzle input, e.g.,
+///
+/// ```text
+///     [D]    
+/// [N] [C]    
+/// [Z] [M] [P]
+///  1   2   3
+/// ```
+///
+/// Every line is padded out to the full width of the drawing (as in the
+/// puzzle inputs), and there's no newline after the labels, so parsing a
+/// drawing and then displaying it gives back exactly the same text.
+impl Display for Stacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row = self
+                .stacks
+                .iter()
+                .map(|stack| {
+                    stack
+                        .get(level)
+                        .map_or_else(|| "   ".to_string(), |c| format!("[{c}]"))
+                })
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        let labels = (1..=self.stacks.len())
+            .map(|label| format!("{label:^3}"))
+            .collect::<Vec<_>>();
+        write!(f, "{}", labels.join(" "))
+    }
+}
+
 impl Stacks {
+    fn new(num_stacks: usize) -> Self {
+        Self {
+            stacks: vec![Vec::new(); num_stacks],
+        }
+    }
+
     // Note that the argument here is `self` and not `&self` because we
     // need to take ownership of this `Stacks` value so we can mutate
     // it in the `fold()` call. Alternatively we could declare this
@@ -99,26 +160,48 @@ impl Stacks {
 
     fn apply(
         mut self,
+        crane: Crane,
         Instruction {
             num_to_move,
             from_stack,
             to_stack,
-        }: Instruction,
+        }: &Instruction,
     ) -> Result<Self> {
+        let num_stacks = self.stacks.len();
+        ensure!(
+            (1..=num_stacks).contains(from_stack) && (1..=num_stacks).contains(to_stack),
+            "We tried to move from stack {from_stack} to stack {to_stack}, but the stacks are numbered 1 to {num_stacks}"
+        );
+
         // We know from the parsing check that `from_stack` and `to_stack` are different.
         let (source, destination) =
             Self::get_two_mut(&mut self.stacks, from_stack - 1, to_stack - 1);
 
         ensure!(
-            num_to_move <= source.len(),
+            *num_to_move <= source.len(),
             "We tried to take {num_to_move} items from {source:?}"
         );
 
-        let crates_to_move = source.drain((source.len() - num_to_move)..);
-        destination.extend(crates_to_move);
+        // The crane moves as many crates as it can lift at a time, keeping
+        // the crates in each lift in the same order.
+        let mut num_left = *num_to_move;
+        while num_left > 0 {
+            let lift_size = num_left.min(crane.max_lift());
+            let crates_to_move = source.drain((source.len() - lift_size)..);
+            destination.extend(crates_to_move);
+            num_left -= lift_size;
+        }
         Ok(self)
     }
 
+    // Like `tops_string()`, but with a space for any empty stacks.
+    fn tops(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|s| s.last().copied().unwrap_or(' '))
+            .collect()
+    }
+
     fn tops_string(&self) -> Result<String> {
         self.stacks
             .iter()
@@ -153,6 +236,12 @@ mod stacks_from_str_tests {
         assert_eq!(vec!['M', 'C', 'D'], stacks.stacks[1]);
         assert_eq!(1, stacks.stacks[2].len());
     }
+
+    #[test]
+    fn bad_labels() {
+        assert!("[A]\n 1   3".parse::<Stacks>().is_err());
+        assert!("[A]\n".parse::<Stacks>().is_err());
+    }
 }
 
 struct Instruction {
@@ -193,7 +282,87 @@ impl FromStr for Instruction {
     }
 }
 
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.num_to_move, self.from_stack, self.to_stack
+        )
+    }
+}
+
+/// The model of crane doing the moving: the `CrateMover9000` from part 1
+/// moves one crate at a time, the `CrateMover9001` from part 2 moves all
+/// the crates in an instruction at once, and we can also have cranes
+/// that can lift some other number of crates at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Crane {
+    CrateMover9000,
+    CrateMover9001,
+    MaxLift(usize),
+}
+
+impl Crane {
+    const fn max_lift(self) -> usize {
+        match self {
+            Self::CrateMover9000 => 1,
+            Self::CrateMover9001 => usize::MAX,
+            Self::MaxLift(max_lift) => max_lift,
+        }
+    }
+}
+
+impl FromStr for Crane {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "9000" => Self::CrateMover9000,
+            "9001" => Self::CrateMover9001,
+            _ => {
+                let max_lift = s.parse().with_context(|| {
+                    format!("Unknown crane '{s}'; expected 9000, 9001, or a max lift size")
+                })?;
+                ensure!(
+                    max_lift > 0,
+                    "A crane has to be able to lift at least one crate"
+                );
+                Self::MaxLift(max_lift)
+            }
+        })
+    }
+}
+
+static USAGE: &str = "Usage: [--crane 9000|9001|<max lift>] [--trace | --trace-stacks]";
+
+/// How much to print after each instruction: nothing, the tops of the
+/// stacks, or the full drawing of the stacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trace {
+    Off,
+    Tops,
+    Stacks,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<(Crane, Trace)> {
+    let mut crane = Crane::CrateMover9001;
+    let mut trace = Trace::Off;
+    let mut args = args;
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--crane" => crane = args.next().context(USAGE)?.parse()?,
+            "--trace" => trace = Trace::Tops,
+            "--trace-stacks" => trace = Trace::Stacks,
+            _ => bail!("Unknown option '{flag}'\n{USAGE}"),
+        }
+    }
+    Ok((crane, trace))
+}
+
 fn main() -> Result<()> {
+    let (crane, trace) = parse_args(env::args().skip(1))?;
+
     let contents = fs::read_to_string(INPUT_FILE)
         .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
 
@@ -208,7 +377,17 @@ fn main() -> Result<()> {
         .map(str::parse)
         .collect::<Result<Vec<_>>>()?;
 
-    let final_state = instructions.into_iter().try_fold(stacks, Stacks::apply)?;
+    let final_state = instructions
+        .iter()
+        .try_fold(stacks, |stacks, instruction| {
+            let stacks = stacks.apply(crane, instruction)?;
+            match trace {
+                Trace::Off => {}
+                Trace::Tops => println!("{instruction:<20} tops: {}", stacks.tops()),
+                Trace::Stacks => println!("{instruction}\n{stacks}\n"),
+            }
+            Ok::<_, anyhow::Error>(stacks)
+        })?;
 
     println!("The top of the stacks is {}", final_state.tops_string()?);
 
@@ -222,7 +401,101 @@ mod extract_stack_elements_test {
     #[test]
     fn extract() {
         let line = "[S] [J] [C]     [F] [C]     [D] [G]";
-        let result = extract_stack_elements(line);
+        let result = extract_stack_elements(line, 9);
         assert_eq!(result, vec!['S', 'J', 'C', ' ', 'F', 'C', ' ', 'D', 'G']);
     }
 }
+
+#[cfg(test)]
+mod crane_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    fn run(crane: Crane) -> Result<String> {
+        let (stack_config, instructions) = EXAMPLE
+            .split_once("\n\n")
+            .context("There was no blank line in the example")?;
+        instructions
+            .lines()
+            .map(str::parse::<Instruction>)
+            .try_fold(stack_config.parse::<Stacks>()?, |stacks, instruction| {
+                stacks.apply(crane, &instruction?)
+            })?
+            .tops_string()
+    }
+
+    #[test]
+    fn crane_models() -> Result<()> {
+        assert_eq!("CMZ", run(Crane::CrateMover9000)?);
+        assert_eq!("MCD", run(Crane::CrateMover9001)?);
+        assert_eq!("CMZ", run("1".parse()?)?);
+        assert_eq!(Crane::MaxLift(2), "2".parse()?);
+        assert!("0".parse::<Crane>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_stack() -> Result<()> {
+        let stacks: Stacks = "[A]\n 1".parse()?;
+        assert!(stacks
+            .apply(Crane::CrateMover9001, &"move 1 from 1 to 2".parse()?)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn stack_zero() -> Result<()> {
+        let stacks: Stacks = "[A]     \n 1   2 ".parse()?;
+        assert!(stacks
+            .apply(Crane::CrateMover9001, &"move 1 from 0 to 2".parse()?)
+            .is_err());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+
+    fn round_trip(drawing: &str) -> Result<()> {
+        let stacks: Stacks = drawing.parse()?;
+        assert_eq!(drawing, stacks.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn example_round_trip() -> Result<()> {
+        round_trip("    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ")
+    }
+
+    #[test]
+    fn input_round_trip() -> Result<()> {
+        let contents = fs::read_to_string(INPUT_FILE)?;
+        let (stack_config, _) = contents
+            .split_once("\n\n")
+            .context("There was no blank line in the input")?;
+        round_trip(stack_config)
+    }
+
+    #[test]
+    fn render_after_moves() -> Result<()> {
+        let stacks: Stacks = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ".parse()?;
+        let stacks = stacks.apply(Crane::CrateMover9001, &"move 2 from 2 to 3".parse()?)?;
+        assert_eq!(
+            "        [D]\n[N]     [C]\n[Z] [M] [P]\n 1   2   3 ",
+            stacks.to_string()
+        );
+        // Empty stacks still get a label.
+        assert_eq!(" 1   2 ", Stacks::new(2).to_string());
+        Ok(())
+    }
+}
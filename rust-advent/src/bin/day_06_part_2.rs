@@ -3,48 +3,131 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{Context, Result};
-use std::{collections::HashSet, fs};
+use anyhow::{ensure, Context, Result};
+use std::{env, fs::File, io::Read};
 
 static INPUT_FILE: &str = "../inputs/day_06.input";
 
 const WINDOW_SIZE: usize = 14;
 
-// TODO: Try using Rayon's `par_windows()` and do some benchmarking
-//  to see how much faster that makes things.
-fn find_marker(s: &str) -> Result<usize> {
-    let (pos, _) = s
-        .as_bytes()
-        .windows(WINDOW_SIZE)
-        .enumerate()
-        .find(|(_, cs)| all_unique(cs))
-        .context("There were no unique windows in the input")?;
-
-    // The marker is four characters ahead of the start of the first block
-    // of unique characters.
-    Ok(pos + WINDOW_SIZE)
+/**
+ * Finds markers (windows of distinct bytes) in a single pass over the
+ * datastream, one byte at a time, so the datastream can be read in as
+ * it arrives.
+ *
+ * For each byte value we remember where we last saw it, and we keep
+ * track of where the current run of distinct bytes starts. When we
+ * see a byte that's already in the current run, the run has to start
+ * just after that earlier copy. The window of size `w` ending at the
+ * current byte is a marker exactly when the run is at least `w` bytes
+ * long, so the one run answers every window size at once.
+ */
+struct MarkerScanner {
+    // One more than the last position each byte was seen at, so that
+    // 0 can mean "never seen".
+    last_seen: [usize; 256],
+    run_start: usize,
+    position: usize,
 }
 
-fn all_unique(cs: &[u8]) -> bool {
-    // This works because hashing on &T is converting to hashing
-    // on T, so we end up hashing on the underlying `u8`s (and
-    // not their addresses as I had thought we would), which
-    // makes everything work.
-    // See https://doc.rust-lang.org/std/hash/trait.Hash.html#impl-Hash-for-%26T
-    // and click on the `source` to see what actually happens.
-    // Thanks to @esitsu for pointing this out, in response to a
-    // question from @ikopor.
-    let unique_chars: HashSet<&u8> = cs.iter().collect();
-    cs.len() == unique_chars.len()
+impl MarkerScanner {
+    const fn new() -> Self {
+        Self {
+            last_seen: [0; 256],
+            run_start: 0,
+            position: 0,
+        }
+    }
+
+    /// Add the next byte of the datastream, returning the length of the
+    /// run of distinct bytes that ends with this byte.
+    fn push(&mut self, byte: u8) -> usize {
+        let seen = &mut self.last_seen[usize::from(byte)];
+        self.run_start = self.run_start.max(*seen);
+        self.position += 1;
+        *seen = self.position;
+        self.position - self.run_start
+    }
+}
+
+/// All the marker positions (the number of bytes read when the marker is
+/// complete) for each of the window sizes.
+#[derive(Debug, PartialEq, Eq)]
+struct Markers {
+    window_sizes: Vec<usize>,
+    positions: Vec<Vec<usize>>,
+}
+
+impl Markers {
+    fn first(&self, window_size: usize) -> Option<usize> {
+        let index = self.window_sizes.iter().position(|&w| w == window_size)?;
+        self.positions[index].first().copied()
+    }
+}
+
+/// Scan the datastream from `reader` for markers of each of the given
+/// sizes. The datastream ends at the first newline (or the end of the
+/// input).
+fn scan_markers(mut reader: impl Read, window_sizes: &[usize]) -> Result<Markers> {
+    ensure!(
+        window_sizes.iter().all(|&w| w > 0),
+        "Window sizes have to be positive, but got {window_sizes:?}"
+    );
+    let mut scanner = MarkerScanner::new();
+    let mut positions = vec![Vec::new(); window_sizes.len()];
+    let mut buffer = [0; 8192];
+    'reading: loop {
+        let num_read = reader
+            .read(&mut buffer)
+            .context("Failed to read the datastream")?;
+        if num_read == 0 {
+            break;
+        }
+        for &byte in &buffer[..num_read] {
+            if byte == b'\n' {
+                break 'reading;
+            }
+            let run_length = scanner.push(byte);
+            for (window_size, positions) in window_sizes.iter().zip(&mut positions) {
+                if run_length >= *window_size {
+                    positions.push(scanner.position);
+                }
+            }
+        }
+    }
+    Ok(Markers {
+        window_sizes: window_sizes.to_vec(),
+        positions,
+    })
 }
 
 fn main() -> Result<()> {
-    let contents = fs::read_to_string(INPUT_FILE)
-        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+    let window_sizes = env::args()
+        .skip(1)
+        .map(|arg| {
+            arg.parse()
+                .with_context(|| format!("Couldn't parse window size '{arg}'"))
+        })
+        .collect::<Result<Vec<usize>>>()?;
+    let window_sizes = if window_sizes.is_empty() {
+        vec![WINDOW_SIZE]
+    } else {
+        window_sizes
+    };
 
-    let marker = find_marker(&contents)?;
+    let file =
+        File::open(INPUT_FILE).with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+    let markers = scan_markers(file, &window_sizes)?;
 
-    println!("The marker was {marker}");
+    for (window_size, positions) in markers.window_sizes.iter().zip(&markers.positions) {
+        let marker = markers
+            .first(*window_size)
+            .with_context(|| format!("There were no unique windows of size {window_size}"))?;
+        println!(
+            "The marker for window size {window_size} was {marker} (one of {} marker positions)",
+            positions.len()
+        );
+    }
 
     Ok(())
 }
@@ -56,8 +139,27 @@ mod tests {
     #[test]
     fn third_example() -> Result<()> {
         let input = "nppdvjthqldpwncqszvftbrmjlhg";
-        let result = find_marker(input)?;
-        assert_eq!(23, result);
+        let result = scan_markers(input.as_bytes(), &[WINDOW_SIZE])?.first(WINDOW_SIZE);
+        assert_eq!(Some(23), result);
+        Ok(())
+    }
+
+    #[test]
+    fn several_window_sizes() -> Result<()> {
+        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb\nabcd";
+        let markers = scan_markers(input.as_bytes(), &[4, 14])?;
+        assert_eq!(Some(7), markers.first(4));
+        assert_eq!(Some(19), markers.first(14));
+        assert_eq!(None, markers.first(3));
+        // The marker positions stop at the newline.
+        assert_eq!(vec![19, 25, 26, 27, 28, 29, 30], markers.positions[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn no_marker() -> Result<()> {
+        assert_eq!(None, scan_markers(&b"aaaa"[..], &[2])?.first(2));
+        assert!(scan_markers(&b"ab"[..], &[0]).is_err());
         Ok(())
     }
 }
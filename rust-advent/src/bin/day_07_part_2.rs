@@ -3,32 +3,298 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use itertools::{Either, Itertools};
 use std::{
-    collections::HashMap,
-    fs::{self},
-    path::PathBuf,
+    collections::{BTreeMap, HashMap},
+    env,
+    fmt::Display,
+    fs,
     str::FromStr,
 };
 
 static INPUT_FILE: &str = "../inputs/day_07.input";
 
-// `PathBuf` is the path to a directory.
-// `usize` will the the associated size of that directory.
-type DirectoryMap = HashMap<PathBuf, usize>;
+const DISK_SIZE: usize = 70_000_000;
+const REQUIRED_SPACE: usize = 30_000_000;
+
+// The index of a directory in the `FileSystem`'s list of directories.
+type DirectoryId = usize;
+
+const ROOT: DirectoryId = 0;
+
+#[derive(Debug)]
+struct Directory {
+    name: String,
+    parent: Option<DirectoryId>,
+    subdirectories: BTreeMap<String, DirectoryId>,
+    files: BTreeMap<String, usize>,
+}
+
+impl Directory {
+    const fn new(name: String, parent: Option<DirectoryId>) -> Self {
+        Self {
+            name,
+            parent,
+            subdirectories: BTreeMap::new(),
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+/**
+ * The tree of directories and files described by the transcript. The
+ * directories are all kept in one `Vec` and refer to each other by
+ * their index in that `Vec`, which saves us from fighting the borrow
+ * checker over parent pointers. The root directory is always at index
+ * 0, and every directory comes after its parent.
+ */
+#[derive(Debug)]
+struct FileSystem {
+    directories: Vec<Directory>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self {
+            directories: vec![Directory::new("/".to_string(), None)],
+        }
+    }
+}
+
+impl FileSystem {
+    // Returns the existing subdirectory if there already is one with this name.
+    fn add_subdirectory(&mut self, parent: DirectoryId, name: &str) -> DirectoryId {
+        if let Some(id) = self.directories[parent].subdirectories.get(name) {
+            return *id;
+        }
+        let id = self.directories.len();
+        self.directories
+            .push(Directory::new(name.to_string(), Some(parent)));
+        self.directories[parent]
+            .subdirectories
+            .insert(name.to_string(), id);
+        id
+    }
+
+    fn add_file(&mut self, directory: DirectoryId, name: &str, size: usize) {
+        self.directories[directory]
+            .files
+            .insert(name.to_string(), size);
+    }
+
+    /// The total size of every directory (including everything in its
+    /// subdirectories), indexed by `DirectoryId`.
+    fn sizes(&self) -> Vec<usize> {
+        let mut sizes = self
+            .directories
+            .iter()
+            .map(|directory| directory.files.values().sum())
+            .collect::<Vec<usize>>();
+        // Every directory comes after its parent, so going backwards we've
+        // always finished a directory before we add it to its parent.
+        for (id, directory) in self.directories.iter().enumerate().rev() {
+            if let Some(parent) = directory.parent {
+                sizes[parent] += sizes[id];
+            }
+        }
+        sizes
+    }
+
+    fn path(&self, directory: DirectoryId) -> String {
+        match self.directories[directory].parent {
+            None => "/".to_string(),
+            Some(ROOT) => format!("/{}", self.directories[directory].name),
+            Some(parent) => format!("{}/{}", self.path(parent), self.directories[directory].name),
+        }
+    }
+
+    /// The directories in depth-first order (sorted by name within each
+    /// directory), along with their depth, where `/` has depth 0.
+    fn walk(&self) -> Vec<(DirectoryId, usize)> {
+        let mut result = Vec::new();
+        let mut to_visit = vec![(ROOT, 0)];
+        while let Some((id, depth)) = to_visit.pop() {
+            result.push((id, depth));
+            to_visit.extend(
+                self.directories[id]
+                    .subdirectories
+                    .values()
+                    .rev()
+                    .map(|child| (*child, depth + 1)),
+            );
+        }
+        result
+    }
+
+    /// Like `du -d <max_depth>`: the path and total size of every
+    /// directory that's at most `max_depth` below `/`.
+    fn du(&self, max_depth: usize) -> Vec<(String, usize)> {
+        let sizes = self.sizes();
+        self.walk()
+            .into_iter()
+            .filter(|(_, depth)| *depth <= max_depth)
+            .map(|(id, _)| (self.path(id), sizes[id]))
+            .collect()
+    }
+
+    /// The path and total size of every directory whose size satisfies
+    /// `predicate`.
+    fn find(&self, predicate: impl Fn(usize) -> bool) -> Vec<(String, usize)> {
+        let sizes = self.sizes();
+        self.walk()
+            .into_iter()
+            .filter(|(id, _)| predicate(sizes[*id]))
+            .map(|(id, _)| (self.path(id), sizes[id]))
+            .collect()
+    }
+
+    /// The smallest directory we can delete to get `required_space` free on
+    /// a disk of size `disk_size`.
+    fn smallest_deletion(
+        &self,
+        disk_size: usize,
+        required_space: usize,
+    ) -> Result<(String, usize)> {
+        let total_used = self.sizes()[ROOT];
+        let total_free = disk_size.checked_sub(total_used).with_context(|| {
+            format!("We're using {total_used}, which is more than the disk size {disk_size}")
+        })?;
+        let total_needed = required_space.saturating_sub(total_free);
+        self.find(|size| size >= total_needed)
+            .into_iter()
+            .min_by_key(|(_, size)| *size)
+            .context("There were no directories that were big enough")
+    }
+}
+
+/// Draw the tree the way the puzzle description does, e.g.,
+///
+/// ```text
+/// - / (dir)
+///   - a (dir)
+///     - e (dir)
+///       - i (file, size=584)
+///     - f (file, size=29116)
+/// ```
+impl Display for FileSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.draw(ROOT, 0, f)
+    }
+}
+
+impl FileSystem {
+    fn draw(
+        &self,
+        id: DirectoryId,
+        depth: usize,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        let directory = &self.directories[id];
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{indent}- {} (dir)", directory.name)?;
+        // The puzzle lists the directories and files together, sorted by name.
+        let entries = directory
+            .subdirectories
+            .iter()
+            .map(|(name, child)| (name, Either::Left(*child)))
+            .merge_by(
+                directory
+                    .files
+                    .iter()
+                    .map(|(name, size)| (name, Either::Right(*size))),
+                |(dir_name, _), (file_name, _)| dir_name <= file_name,
+            );
+        for (name, entry) in entries {
+            match entry {
+                Either::Left(child) => self.draw(child, depth + 1, f)?,
+                Either::Right(size) => writeln!(f, "{indent}  - {name} (file, size={size})")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Something in the transcript that doesn't make sense for a real
+/// shell session. None of these stop us from building the tree, but
+/// they suggest the transcript (or the tree) can't be trusted.
+#[derive(Debug, PartialEq, Eq)]
+enum AnomalyKind {
+    DuplicateListing {
+        path: String,
+        first_line: usize,
+    },
+    UnknownDirectory {
+        path: String,
+    },
+    ConflictingFileSize {
+        path: String,
+        old_size: usize,
+        new_size: usize,
+    },
+    CdAboveRoot,
+    OutputWithoutLs,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Anomaly {
+    line_number: usize,
+    kind: AnomalyKind,
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: ", self.line_number)?;
+        match &self.kind {
+            AnomalyKind::DuplicateListing { path, first_line } => {
+                write!(f, "{path} was already listed on line {first_line}")
+            }
+            AnomalyKind::UnknownDirectory { path } => {
+                write!(f, "cd into {path}, which hadn't been seen in a listing")
+            }
+            AnomalyKind::ConflictingFileSize {
+                path,
+                old_size,
+                new_size,
+            } => write!(
+                f,
+                "{path} has size {new_size}, but was listed with size {old_size}"
+            ),
+            AnomalyKind::CdAboveRoot => write!(f, "cd .. while already in /"),
+            AnomalyKind::OutputWithoutLs => write!(f, "ls output without a preceding ls"),
+        }
+    }
+}
 
-#[derive(Default)]
 struct FileSystemState {
-    directory_map: DirectoryMap,
-    current_directory: PathBuf,
+    file_system: FileSystem,
+    current_directory: DirectoryId,
+    // Whether the lines we're reading are the output of an `ls`.
+    listing: bool,
+    // The line each directory was first listed on.
+    listed: HashMap<DirectoryId, usize>,
+    line_number: usize,
+    anomalies: Vec<Anomaly>,
+}
+
+impl Default for FileSystemState {
+    fn default() -> Self {
+        Self {
+            file_system: FileSystem::default(),
+            current_directory: ROOT,
+            listing: false,
+            listed: HashMap::new(),
+            line_number: 0,
+            anomalies: Vec::new(),
+        }
+    }
 }
 
 enum InputLine {
     Cd(String),
     Ls,
-    // Do we actually need to record the directory name here?
     Dir(String),
-    File(usize),
+    File { name: String, size: usize },
 }
 
 impl FromStr for InputLine {
@@ -36,17 +302,18 @@ impl FromStr for InputLine {
 
     fn from_str(line: &str) -> Result<Self> {
         let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
-        let first_part = parts
-            .first()
-            .with_context(|| "The line '{line}' didn't have a first part")?;
-        let entry = match *first_part {
-            "$" => match parts.get(1) {
-                Some(&"cd") => Self::Cd(parts[2].to_string()),
-                Some(&"ls") => Self::Ls,
-                _ => bail!("Unknown command {} (should be 'cd' or 'ls')", parts[1]),
+        let entry = match parts[..] {
+            ["$", "cd", directory_name] => Self::Cd(directory_name.to_string()),
+            ["$", "ls"] => Self::Ls,
+            ["$", command, ..] => bail!("Unknown command {command} (should be 'cd' or 'ls')"),
+            ["dir", directory_name] => Self::Dir(directory_name.to_string()),
+            [size, name] => Self::File {
+                name: name.to_string(),
+                size: size
+                    .parse()
+                    .with_context(|| format!("Couldn't parse the file size '{size}'"))?,
             },
-            "dir" => Self::Dir(parts[1].to_string()),
-            _ => Self::File(parts[0].parse()?),
+            _ => bail!("The line '{line}' wasn't a command, directory, or file"),
         };
 
         Ok(entry)
@@ -54,7 +321,9 @@ impl FromStr for InputLine {
 }
 
 impl FileSystemState {
-    fn process_input_line(self, input_line: InputLine) -> Self {
+    fn process_input_line(mut self, input_line: InputLine) -> Self {
+        self.line_number += 1;
+
         // NOTE that we can import all the enum types and just use, e.g, `Dir(directory_name)`
         // instead of `InputLine::Dir(directory_name). Thanks to NathanielBumppo@Twitch for
         // the suggestion. I'm not using it here based in part on a comment from
@@ -64,9 +333,30 @@ impl FileSystemState {
         // use InputLine::*;
 
         match input_line {
-            InputLine::Cd(directory_name) => self.handle_cd(directory_name),
-            InputLine::Ls | InputLine::Dir(_) => self,
-            InputLine::File(size) => self.handle_file(size),
+            InputLine::Cd(directory_name) => self.handle_cd(&directory_name),
+            InputLine::Ls => self.handle_ls(),
+            InputLine::Dir(directory_name) => self.handle_dir(&directory_name),
+            InputLine::File { name, size } => self.handle_file(&name, size),
+        }
+    }
+
+    fn report(&mut self, kind: AnomalyKind) {
+        self.anomalies.push(Anomaly {
+            line_number: self.line_number,
+            kind,
+        });
+    }
+
+    fn check_listing(&mut self) {
+        if !self.listing {
+            self.report(AnomalyKind::OutputWithoutLs);
+        }
+    }
+
+    fn path_to(&self, name: &str) -> String {
+        match self.current_directory {
+            ROOT => format!("/{name}"),
+            _ => format!("{}/{name}", self.file_system.path(self.current_directory)),
         }
     }
 
@@ -74,37 +364,83 @@ impl FileSystemState {
     //   DotDot, and Name), then this would be a `match` clause and I wouldn't
     //   have been able to have forgotten about the DotDot case like I did the
     //   first time.
-    fn handle_cd(mut self, directory_name: String) -> Self {
-        if directory_name == "/" {
-            self.current_directory = PathBuf::from("/");
-        } else if directory_name == ".." {
-            self.current_directory.pop();
+    fn handle_cd(mut self, directory_name: &str) -> Self {
+        self.listing = false;
+        self.current_directory = match directory_name {
+            "/" => ROOT,
+            ".." => {
+                let parent = self.file_system.directories[self.current_directory].parent;
+                if parent.is_none() {
+                    self.report(AnomalyKind::CdAboveRoot);
+                }
+                parent.unwrap_or(ROOT)
+            }
+            _ => {
+                if !self.file_system.directories[self.current_directory]
+                    .subdirectories
+                    .contains_key(directory_name)
+                {
+                    let path = self.path_to(directory_name);
+                    self.report(AnomalyKind::UnknownDirectory { path });
+                }
+                self.file_system
+                    .add_subdirectory(self.current_directory, directory_name)
+            }
+        };
+        self
+    }
+
+    fn handle_ls(mut self) -> Self {
+        self.listing = true;
+        if let Some(first_line) = self.listed.get(&self.current_directory) {
+            let kind = AnomalyKind::DuplicateListing {
+                path: self.file_system.path(self.current_directory),
+                first_line: *first_line,
+            };
+            self.report(kind);
         } else {
-            self.current_directory.push(directory_name);
+            self.listed.insert(self.current_directory, self.line_number);
         }
         self
     }
 
-    fn handle_file(mut self, file_size: usize) -> Self {
-        // If the file size is 10 and the current directory is "/a/b/c"
-        // we need to add 10 to "/a/b/c", "/a/b", "/a", and "/".
-        for directory in self.current_directory.ancestors() {
-            *self
-                .directory_map
-                .entry(directory.to_path_buf())
-                .or_insert(0) += file_size;
+    fn handle_dir(mut self, directory_name: &str) -> Self {
+        self.check_listing();
+        self.file_system
+            .add_subdirectory(self.current_directory, directory_name);
+        self
+    }
+
+    fn handle_file(mut self, name: &str, size: usize) -> Self {
+        self.check_listing();
+        if let Some(&old_size) = self.file_system.directories[self.current_directory]
+            .files
+            .get(name)
+        {
+            if old_size != size {
+                let path = self.path_to(name);
+                self.report(AnomalyKind::ConflictingFileSize {
+                    path,
+                    old_size,
+                    new_size: size,
+                });
+            }
         }
+        self.file_system
+            .add_file(self.current_directory, name, size);
         self
     }
 }
 
-fn parse_commands(contents: &str) -> Result<FileSystemState> {
+/// Build the tree from the transcript in `contents`, along with anything
+/// odd we noticed along the way.
+fn parse_commands(contents: &str) -> Result<(FileSystem, Vec<Anomaly>)> {
     let final_state: FileSystemState = contents
         .lines()
         .map(|line_str| {
             line_str
                 .parse::<InputLine>()
-                .with_context(|| "The line {line_str} failed to parse to an `InputLine`")
+                .with_context(|| format!("The line {line_str} failed to parse to an `InputLine`"))
         })
         .try_fold(
             FileSystemState::default(),
@@ -112,41 +448,200 @@ fn parse_commands(contents: &str) -> Result<FileSystemState> {
                 Ok(file_system_state.process_input_line(input_line?))
             },
         )?;
-    Ok(final_state)
-}
-
-fn find_directory_to_delete(file_system_state: &FileSystemState) -> Result<usize> {
-    let directory_map = &file_system_state.directory_map;
-    let total_used = directory_map
-        .get(&PathBuf::from("/"))
-        .context("The directory map didn't have an entry for '/'")?;
-    let total_free = 70_000_000 - total_used;
-    let total_needed = 30_000_000 - total_free;
-    let result = file_system_state
-        .directory_map
-        .iter()
-        .filter_map(|(_, size)| {
-            if *size >= total_needed {
-                Some(size)
-            } else {
-                None
-            }
-        })
-        .min()
-        .copied()
-        .context("There were no directories that were big enough")?;
-    Ok(result)
+    Ok((final_state.file_system, final_state.anomalies))
+}
+
+static USAGE: &str =
+    "Usage: [du <max depth> | tree | find <max size> | validate | delete [<disk size> <required space>]]";
+
+fn parse_number(s: &str) -> Result<usize> {
+    s.parse()
+        .with_context(|| format!("Couldn't parse '{s}' to a number\n{USAGE}"))
 }
 
 fn main() -> Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
     let contents = fs::read_to_string(INPUT_FILE)
         .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
 
-    let file_system_state: FileSystemState = parse_commands(&contents)?;
-
-    let total_sizes = find_directory_to_delete(&file_system_state)?;
+    let (file_system, anomalies) = parse_commands(&contents)?;
 
-    println!("The total of the sizes was {total_sizes}");
+    match args[..] {
+        ["validate"] => {
+            for anomaly in &anomalies {
+                println!("{anomaly}");
+            }
+            ensure!(
+                anomalies.is_empty(),
+                "There were {} anomalies in the transcript",
+                anomalies.len()
+            );
+            println!("The transcript had no anomalies");
+        }
+        ["du", max_depth] => {
+            for (path, size) in file_system.du(parse_number(max_depth)?) {
+                println!("{size}\t{path}");
+            }
+        }
+        ["tree"] => print!("{file_system}"),
+        ["find", max_size] => {
+            let max_size = parse_number(max_size)?;
+            for (path, size) in file_system.find(|size| size <= max_size) {
+                println!("{size}\t{path}");
+            }
+        }
+        [] | ["delete"] => {
+            let (_, size) = file_system.smallest_deletion(DISK_SIZE, REQUIRED_SPACE)?;
+            println!("The total of the sizes was {size}");
+        }
+        ["delete", disk_size, required_space] => {
+            let (path, size) = file_system
+                .smallest_deletion(parse_number(disk_size)?, parse_number(required_space)?)?;
+            println!("Deleting {path} would free up {size}");
+        }
+        _ => bail!("{USAGE}"),
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod file_system_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+
+    #[test]
+    fn tree() -> Result<()> {
+        let (file_system, anomalies) = parse_commands(EXAMPLE)?;
+        assert!(anomalies.is_empty());
+        assert_eq!(
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+",
+            file_system.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn queries() -> Result<()> {
+        let (file_system, _) = parse_commands(EXAMPLE)?;
+        assert_eq!(
+            vec![
+                ("/".to_string(), 48_381_165),
+                ("/a".to_string(), 94_853),
+                ("/d".to_string(), 24_933_642)
+            ],
+            file_system.du(1)
+        );
+        assert_eq!(
+            95_437,
+            file_system
+                .find(|size| size <= 100_000)
+                .iter()
+                .map(|(_, size)| size)
+                .sum::<usize>()
+        );
+        assert_eq!(
+            ("/d".to_string(), 24_933_642),
+            file_system.smallest_deletion(DISK_SIZE, REQUIRED_SPACE)?
+        );
+        assert_eq!(
+            ("/a/e".to_string(), 584),
+            file_system.smallest_deletion(48_381_165, 0)?
+        );
+        assert!(file_system.smallest_deletion(100, 10).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn anomalies() -> Result<()> {
+        let transcript = "$ cd /
+$ ls
+dir a
+10 b.txt
+$ cd ..
+$ cd a
+$ ls
+5 c
+$ cd x
+7 d
+$ cd ..
+$ cd /
+$ ls
+11 b.txt";
+        let (file_system, anomalies) = parse_commands(transcript)?;
+        let kinds = anomalies
+            .into_iter()
+            .map(|anomaly| (anomaly.line_number, anomaly.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (5, AnomalyKind::CdAboveRoot),
+                (
+                    9,
+                    AnomalyKind::UnknownDirectory {
+                        path: "/a/x".to_string()
+                    }
+                ),
+                (10, AnomalyKind::OutputWithoutLs),
+                (
+                    13,
+                    AnomalyKind::DuplicateListing {
+                        path: "/".to_string(),
+                        first_line: 2
+                    }
+                ),
+                (
+                    14,
+                    AnomalyKind::ConflictingFileSize {
+                        path: "/b.txt".to_string(),
+                        old_size: 10,
+                        new_size: 11
+                    }
+                ),
+            ],
+            kinds
+        );
+        // We still build the tree, keeping the latest size we saw.
+        assert_eq!(("/".to_string(), 23), file_system.du(0)[0]);
+        Ok(())
+    }
+}
@@ -3,60 +3,141 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{Context, Result};
-use rayon::prelude::{ParallelBridge, ParallelIterator};
-use std::{
-    fs::{self},
-    iter::repeat,
-    str::FromStr,
-};
-use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
+use anyhow::{bail, ensure, Context, Result};
+use ndarray::{s, Array2, ArrayView1, ArrayViewMut1, Axis, Zip};
+use std::{env, fmt::Write as _, fs, io::Write as _, str::FromStr};
 
 #[derive(Debug)]
-struct Tree {
-    height: u8,
+struct Forest {
+    heights: Array2<u8>,
 }
 
-impl Tree {
-    const fn new(height: char) -> Self {
-        Self {
-            height: (height as u8) - b'0',
+impl FromStr for Forest {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rows = s
+            .lines()
+            .enumerate()
+            .map(|(row, line)| {
+                line.chars()
+                    .map(|c| {
+                        c.to_digit(10)
+                            .and_then(|height| u8::try_from(height).ok())
+                            .with_context(|| {
+                                format!("Illegal tree height '{c}' on line {}", row + 1)
+                            })
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let num_rows = rows.len();
+        let num_cols = rows.first().map_or(0, Vec::len);
+        ensure!(num_cols > 0, "The forest is empty");
+        // The forest has to be rectangular, but doesn't have to be square.
+        for (row, trees) in rows.iter().enumerate() {
+            ensure!(
+                trees.len() == num_cols,
+                "Line {} has {} trees, but the first line has {num_cols}",
+                row + 1,
+                trees.len()
+            );
         }
+
+        let heights = Array2::from_shape_vec((num_rows, num_cols), rows.concat())?;
+        Ok(Self { heights })
     }
 }
 
+/// The visibility (part 1) and scenic score (part 2) of every tree in a
+/// `Forest`, indexed by `(row, col)`.
 #[derive(Debug)]
-struct Forest {
-    trees: Vec<Vec<Tree>>,
+struct Views {
+    visible: Array2<bool>,
+    scenic_scores: Array2<usize>,
 }
 
-impl FromStr for Forest {
-    type Err = anyhow::Error;
+impl Views {
+    fn is_visible(&self, row: usize, col: usize) -> Option<bool> {
+        self.visible.get((row, col)).copied()
+    }
 
-    fn from_str(s: &str) -> Result<Self> {
-        let trees = s
-            .lines()
-            .map(|s| s.chars().map(Tree::new).collect::<Vec<_>>())
-            .collect::<Vec<_>>();
+    fn scenic_score(&self, row: usize, col: usize) -> Option<usize> {
+        self.scenic_scores.get((row, col)).copied()
+    }
 
-        let size = trees.len();
+    fn num_visible(&self) -> usize {
+        self.visible.iter().filter(|visible| **visible).count()
+    }
 
-        // Check that the forest is square, sort of. All this
-        // actually checks is that the number of rows is the same
-        // as the number of columns in the first row, but that's
-        // probably sufficient for now.
-        anyhow::ensure!(size == trees[0].len());
+    fn max_scenic_score(&self) -> Option<usize> {
+        self.scenic_scores.iter().max().copied()
+    }
+}
 
-        Ok(Self { trees })
+/**
+ * Look back along a line of trees (towards index 0) from every tree,
+ * marking the trees that can be seen from that end of the line and
+ * multiplying each tree's scenic score by its viewing distance in that
+ * direction.
+ *
+ * This keeps a stack of the trees that could still block the view of
+ * trees further along the line. Each tree pops all the shorter trees off
+ * the stack (since it blocks them from now on), and is then blocked by
+ * whatever is left on top of the stack. If nothing is left, the tree can
+ * see all the way to the edge. Every tree is pushed and popped at most
+ * once, so this takes linear time.
+ */
+fn look_back(
+    heights: ArrayView1<u8>,
+    mut visible: ArrayViewMut1<bool>,
+    mut scenic_scores: ArrayViewMut1<usize>,
+) {
+    let mut stack: Vec<usize> = Vec::with_capacity(heights.len());
+    for (i, &height) in heights.iter().enumerate() {
+        while stack.last().is_some_and(|&j| heights[j] < height) {
+            stack.pop();
+        }
+        match stack.last() {
+            None => {
+                visible[i] = true;
+                scenic_scores[i] *= i;
+            }
+            Some(&blocker) => scenic_scores[i] *= i - blocker,
+        }
+        stack.push(i);
     }
 }
 
-// TODO: As an alternative suggested by esitsu@Twitch, we could
-// skip the whole `Box` thing and bake the types into the `Direction`
-// enum. I might go play with that sometime.
+impl Forest {
+    fn views(&self) -> Views {
+        let mut visible = Array2::from_elem(self.heights.dim(), false);
+        let mut scenic_scores = Array2::from_elem(self.heights.dim(), 1);
+        // The lanes along axis 0 are the columns, and the lanes along
+        // axis 1 are the rows, and we look along each of them in both
+        // directions.
+        for axis in [Axis(0), Axis(1)] {
+            Zip::from(self.heights.lanes(axis))
+                .and(visible.lanes_mut(axis))
+                .and(scenic_scores.lanes_mut(axis))
+                .for_each(|heights, mut visible, mut scenic_scores| {
+                    look_back(heights, visible.view_mut(), scenic_scores.view_mut());
+                    look_back(
+                        heights.slice(s![..;-1]),
+                        visible.slice_mut(s![..;-1]),
+                        scenic_scores.slice_mut(s![..;-1]),
+                    );
+                });
+        }
+        Views {
+            visible,
+            scenic_scores,
+        }
+    }
+}
 
-#[derive(EnumIter, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Up,
     Down,
@@ -64,78 +145,392 @@ enum Direction {
     Right,
 }
 
-struct ForestIterator<T>(Box<dyn Iterator<Item = T>>);
-impl<T> Iterator for ForestIterator<T> {
-    type Item = T;
+impl Direction {
+    const ALL: [Self; 4] = [Self::Up, Self::Down, Self::Left, Self::Right];
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next()
-    }
+/// The trees we see looking out from a tree in one direction, nearest
+/// first, and which of them (if any) blocks the view.
+#[derive(Debug)]
+struct Ray {
+    direction: Direction,
+    trees: Vec<(usize, usize)>,
+    blocker: Option<usize>,
 }
 
-impl ForestIterator<(usize, usize)> {
-    fn neighbors(direction: Direction, row: usize, col: usize, size: usize) -> Self {
-        match direction {
-            Direction::Up => Self(Box::new(repeat(row).zip((0..col).rev()))),
-            Direction::Down => Self(Box::new(repeat(row).zip(col + 1..size))),
-            Direction::Left => Self(Box::new((0..row).rev().zip(repeat(col)))),
-            Direction::Right => Self(Box::new((row + 1..size).zip(repeat(col)))),
-        }
+impl Ray {
+    // The number of trees we can see, including the blocking tree.
+    fn viewing_distance(&self) -> usize {
+        self.blocker.map_or(self.trees.len(), |i| i + 1)
     }
 }
 
 impl Forest {
-    fn size(&self) -> usize {
-        self.trees.len()
-    }
-
-    // 30373
-    // 25512
-    // 65332
-    // 33549
-    // 35390
-
-    fn scenic_score_from(&self, row: usize, col: usize, direction: Direction) -> usize {
-        let this_height = self.trees[row][col].height;
-        let mut neighbors = ForestIterator::neighbors(direction, row, col, self.size());
-        let count = neighbors
-            .by_ref()
-            .take_while(|(other_row, other_col)| {
-                self.trees[*other_row][*other_col].height < this_height
+    /// The four rays out from the tree at `(row, col)`, in the order
+    /// up, down, left, right.
+    fn rays(&self, row: usize, col: usize) -> Result<Vec<Ray>> {
+        let (num_rows, num_cols) = self.heights.dim();
+        let height = *self
+            .heights
+            .get((row, col))
+            .with_context(|| format!("There's no tree at ({row}, {col})"))?;
+        Ok(Direction::ALL
+            .into_iter()
+            .map(|direction| {
+                let trees: Vec<(usize, usize)> = match direction {
+                    Direction::Up => (0..row).rev().map(|r| (r, col)).collect(),
+                    Direction::Down => (row + 1..num_rows).map(|r| (r, col)).collect(),
+                    Direction::Left => (0..col).rev().map(|c| (row, c)).collect(),
+                    Direction::Right => (col + 1..num_cols).map(|c| (row, c)).collect(),
+                };
+                let blocker = trees.iter().position(|pos| self.heights[*pos] >= height);
+                Ray {
+                    direction,
+                    trees,
+                    blocker,
+                }
             })
-            .count();
-        count + usize::from(neighbors.next().is_some())
+            .collect())
     }
 
-    fn scenic_score(&self, row: usize, col: usize) -> usize {
-        Direction::iter()
-            .map(|direction| self.scenic_score_from(row, col, direction))
-            .product()
+    /// Describe the rays from a tree, one line per direction, with the
+    /// heights of the trees along the ray and the blocking tree in
+    /// brackets, e.g.,
+    ///
+    /// ```text
+    /// Up     3 [5]      sees 2, blocked by (1, 2)
+    /// ```
+    fn describe_rays(&self, row: usize, col: usize) -> Result<String> {
+        let mut result = format!(
+            "The tree at ({row}, {col}) has height {}\n",
+            self.heights[(row, col)]
+        );
+        for ray in self.rays(row, col)? {
+            let heights = ray
+                .trees
+                .iter()
+                .enumerate()
+                .map(|(i, pos)| {
+                    let height = self.heights[*pos];
+                    if Some(i) == ray.blocker {
+                        format!("[{height}]")
+                    } else {
+                        height.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let outcome = ray.blocker.map_or_else(
+                || "visible from the edge".to_string(),
+                |i| format!("blocked by {:?}", ray.trees[i]),
+            );
+            writeln!(
+                result,
+                "{:<6} {heights:<20} sees {}, {outcome}",
+                format!("{:?}", ray.direction),
+                ray.viewing_distance()
+            )?;
+        }
+        Ok(result)
     }
 }
 
-fn max_scenic_score(contents: &str) -> Result<usize> {
-    let forest = contents.parse::<Forest>()?;
+/// What a heat map shows: each tree's scenic score, or whether it's
+/// visible from outside the forest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    ScenicScores,
+    Visibility,
+}
 
-    let size = forest.size();
+impl FromStr for Layer {
+    type Err = anyhow::Error;
 
-    (0..size)
-        .flat_map(|col_num| (0..size).zip(repeat(col_num)))
-        .par_bridge()
-        .map(|(row_num, col_num)| forest.scenic_score(row_num, col_num))
-        .max()
-        .context("max() was called on an empty list")
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "scores" => Ok(Self::ScenicScores),
+            "visible" => Ok(Self::Visibility),
+            _ => bail!("Unknown layer '{s}'; expected 'scores' or 'visible'"),
+        }
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Color(u8, u8, u8);
+
+impl Color {
+    const VISIBLE: Self = Self(34, 139, 34);
+    const HIDDEN: Self = Self(40, 40, 40);
+
+    /// A black → red → yellow → white gradient for `fraction` in `[0, 1]`.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn heat(fraction: f64) -> Self {
+        let channel =
+            |start: f64| (fraction.mul_add(3.0, -start).clamp(0.0, 1.0) * 255.0).round() as u8;
+        Self(channel(0.0), channel(1.0), channel(2.0))
+    }
+
+    // Whether black text would be easier to read on this color than white.
+    fn is_light(self) -> bool {
+        let Self(r, g, b) = self;
+        0.114f64.mul_add(
+            f64::from(b),
+            0.299f64.mul_add(f64::from(r), 0.587 * f64::from(g)),
+        ) > 128.0
+    }
+}
+
+impl Views {
+    fn colors(&self, layer: Layer) -> Array2<Color> {
+        match layer {
+            Layer::Visibility => self.visible.mapv(|visible| {
+                if visible {
+                    Color::VISIBLE
+                } else {
+                    Color::HIDDEN
+                }
+            }),
+            Layer::ScenicScores => {
+                // Scores vary over several orders of magnitude, so we use
+                // a log scale to keep everything from looking black.
+                #[allow(clippy::cast_precision_loss)]
+                let log_score = |score: usize| (score as f64).ln_1p();
+                let max = log_score(self.max_scenic_score().unwrap_or(0)).max(f64::EPSILON);
+                self.scenic_scores
+                    .mapv(|score| Color::heat(log_score(score) / max))
+            }
+        }
+    }
+}
+
+/// The forest as a grid of tree heights, with each tree's background
+/// colored using 24-bit ANSI escape codes.
+fn ansi_heat_map(forest: &Forest, colors: &Array2<Color>) -> String {
+    let mut result = String::new();
+    for (heights, colors) in forest.heights.rows().into_iter().zip(colors.rows()) {
+        for (height, color) in heights.iter().zip(colors) {
+            let Color(r, g, b) = *color;
+            let foreground = if color.is_light() { 30 } else { 97 };
+            let _ = write!(result, "\x1b[{foreground};48;2;{r};{g};{b}m{height}");
+        }
+        result.push_str("\x1b[0m\n");
+    }
+    result
+}
+
+/// Write `colors` as a binary PPM image, with each tree drawn as a
+/// `scale`×`scale` square.
+fn write_ppm(colors: &Array2<Color>, scale: usize, mut writer: impl std::io::Write) -> Result<()> {
+    let (num_rows, num_cols) = colors.dim();
+    write!(
+        writer,
+        "P6\n{} {}\n255\n",
+        num_cols * scale,
+        num_rows * scale
+    )?;
+    for row in colors.rows() {
+        let line = row
+            .iter()
+            .flat_map(|Color(r, g, b)| [*r, *g, *b].repeat(scale))
+            .collect::<Vec<_>>();
+        for _ in 0..scale {
+            writer.write_all(&line)?;
+        }
+    }
+    Ok(())
+}
+
+const PIXELS_PER_TREE: usize = 8;
+
 static INPUT_FILE: &str = "../inputs/day_08.input";
 
+static USAGE: &str = "Usage: [heatmap scores|visible | export scores|visible <file.ppm> | ray <row> <col> | <row> <col>...]";
+
+fn parse_position(row: &str, col: &str) -> Result<(usize, usize)> {
+    let row = row
+        .parse()
+        .with_context(|| format!("Couldn't parse row '{row}'"))?;
+    let col = col
+        .parse()
+        .with_context(|| format!("Couldn't parse column '{col}'"))?;
+    Ok((row, col))
+}
+
 fn main() -> Result<()> {
     let contents = fs::read_to_string(INPUT_FILE)
         .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
 
-    let max_score = max_scenic_score(&contents)?;
+    let forest = contents.parse::<Forest>()?;
+    let views = forest.views();
 
-    println!("The maximum scenic score was {max_score}");
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    match args[..] {
+        ["heatmap", layer] => print!("{}", ansi_heat_map(&forest, &views.colors(layer.parse()?))),
+        ["export", layer, file_name] => {
+            let file = fs::File::create(file_name)
+                .with_context(|| format!("Failed to create file '{file_name}'"))?;
+            let mut writer = std::io::BufWriter::new(file);
+            write_ppm(&views.colors(layer.parse()?), PIXELS_PER_TREE, &mut writer)?;
+            writer.flush()?;
+            println!("Wrote the heat map to {file_name}");
+        }
+        ["ray", row, col] => {
+            let (row, col) = parse_position(row, col)?;
+            print!("{}", forest.describe_rays(row, col)?);
+        }
+        _ => {
+            println!("The number of visible trees was {}", views.num_visible());
+
+            let max_score = views
+                .max_scenic_score()
+                .context("max() was called on an empty list")?;
+
+            println!("The maximum scenic score was {max_score}");
+
+            // Any arguments are `<row> <col>` pairs of trees to look up.
+            for position in args.chunks(2) {
+                let [row, col] = position else {
+                    bail!("{USAGE}");
+                };
+                let (row, col) = parse_position(row, col)?;
+                let (Some(visible), Some(score)) =
+                    (views.is_visible(row, col), views.scenic_score(row, col))
+                else {
+                    bail!("There's no tree at ({row}, {col})");
+                };
+                println!(
+                    "The tree at ({row}, {col}) is {}visible and has scenic score {score}",
+                    if visible { "" } else { "not " }
+                );
+            }
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod views_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "30373
+25512
+65332
+33549
+35390";
+
+    #[test]
+    fn example() -> Result<()> {
+        let views = EXAMPLE.parse::<Forest>()?.views();
+        assert_eq!(21, views.num_visible());
+        assert_eq!(Some(8), views.max_scenic_score());
+        assert_eq!(Some(4), views.scenic_score(1, 2));
+        assert_eq!(Some(8), views.scenic_score(3, 2));
+        assert_eq!(Some(false), views.is_visible(1, 3));
+        assert_eq!(Some(true), views.is_visible(2, 1));
+        assert_eq!(None, views.scenic_score(5, 0));
+        Ok(())
+    }
+
+    // Walk each ray from each tree, the slow way.
+    fn brute_force(forest: &Forest, row: usize, col: usize) -> (bool, usize) {
+        let heights = &forest.heights;
+        let (num_rows, num_cols) = heights.dim();
+        let height = heights[(row, col)];
+        let rays: [Vec<(usize, usize)>; 4] = [
+            (0..row).rev().map(|r| (r, col)).collect(),
+            (row + 1..num_rows).map(|r| (r, col)).collect(),
+            (0..col).rev().map(|c| (row, c)).collect(),
+            (col + 1..num_cols).map(|c| (row, c)).collect(),
+        ];
+        let visible = rays
+            .iter()
+            .any(|ray| ray.iter().all(|pos| heights[*pos] < height));
+        let score = rays
+            .iter()
+            .map(|ray| {
+                ray.iter()
+                    .position(|pos| heights[*pos] >= height)
+                    .map_or(ray.len(), |i| i + 1)
+            })
+            .product();
+        (visible, score)
+    }
+
+    #[test]
+    fn rectangular() -> Result<()> {
+        let forest: Forest = "3037311\n2551209\n6533287\n0000009".parse()?;
+        let views = forest.views();
+        let (num_rows, num_cols) = forest.heights.dim();
+        assert_eq!((4, 7), (num_rows, num_cols));
+        for row in 0..num_rows {
+            for col in 0..num_cols {
+                let (visible, score) = brute_force(&forest, row, col);
+                assert_eq!(Some(visible), views.is_visible(row, col));
+                assert_eq!(Some(score), views.scenic_score(row, col));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bad_forests() {
+        assert!("123\n45".parse::<Forest>().is_err());
+        assert!("12a".parse::<Forest>().is_err());
+        assert!("".parse::<Forest>().is_err());
+    }
+}
+
+#[cfg(test)]
+mod rendering_tests {
+    use super::*;
+
+    static EXAMPLE: &str = "30373
+25512
+65332
+33549
+35390";
+
+    #[test]
+    fn rays() -> Result<()> {
+        let forest: Forest = EXAMPLE.parse()?;
+        let rays = forest.rays(3, 2)?;
+        assert_eq!(
+            vec![2, 1, 2, 2],
+            rays.iter().map(Ray::viewing_distance).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Some(1), None, None, Some(1)],
+            rays.iter().map(|ray| ray.blocker).collect::<Vec<_>>()
+        );
+        let description = forest.describe_rays(3, 2)?;
+        assert!(description.contains("Up     3 [5]"));
+        assert!(description.contains("Right  4 [9]"));
+        assert!(description.contains("sees 2, blocked by (3, 4)"));
+        assert!(forest.rays(5, 5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn heat_maps() -> Result<()> {
+        let forest: Forest = EXAMPLE.parse()?;
+        let views = forest.views();
+        let colors = views.colors(Layer::ScenicScores);
+        // The best tree is white hot, and edge trees (score 0) are black.
+        assert_eq!(Color(255, 255, 255), colors[(3, 2)]);
+        assert_eq!(Color(0, 0, 0), colors[(0, 0)]);
+        assert_eq!(Color::HIDDEN, views.colors(Layer::Visibility)[(1, 3)]);
+
+        let heat_map = ansi_heat_map(&forest, &colors);
+        assert_eq!(5, heat_map.lines().count());
+        assert!(heat_map.starts_with("\x1b[97;48;2;0;0;0m3"));
+
+        let mut image = Vec::new();
+        write_ppm(&colors, 2, &mut image)?;
+        let header = b"P6\n10 10\n255\n";
+        assert!(image.starts_with(header));
+        assert_eq!(header.len() + 10 * 10 * 3, image.len());
+        Ok(())
+    }
+}
@@ -3,21 +3,30 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use std::{
     collections::HashSet,
+    env,
     fs::{self},
     str::FromStr,
 };
 
 static INPUT_FILE: &str = "../inputs/day_09.input";
 
-#[derive(Debug, Copy, Clone)]
+/// The direction the head moves in each step of a `Move`. Besides the
+/// four directions in the puzzle, the head can move diagonally (`UL`,
+/// `UR`, `DL`, `DR`) or by any vector (written `<dx>,<dy>`, e.g., `3,-2`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Direction {
     Up,
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    Vector(Position),
 }
 
 impl FromStr for Direction {
@@ -29,17 +38,72 @@ impl FromStr for Direction {
             "D" => Self::Down,
             "L" => Self::Left,
             "R" => Self::Right,
-            _ => bail!("Unknown direction string '{s}'"),
+            "UL" => Self::UpLeft,
+            "UR" => Self::UpRight,
+            "DL" => Self::DownLeft,
+            "DR" => Self::DownRight,
+            _ => {
+                let (x, y) = s
+                    .split_once(',')
+                    .with_context(|| format!("Unknown direction string '{s}'"))?;
+                let parse = |v: &str| {
+                    v.parse::<isize>()
+                        .with_context(|| format!("Couldn't parse '{v}' in the vector '{s}'"))
+                };
+                Self::Vector(Position {
+                    x: parse(x)?,
+                    y: parse(y)?,
+                })
+            }
         })
     }
 }
 
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Up => write!(f, "U"),
+            Self::Down => write!(f, "D"),
+            Self::Left => write!(f, "L"),
+            Self::Right => write!(f, "R"),
+            Self::UpLeft => write!(f, "UL"),
+            Self::UpRight => write!(f, "UR"),
+            Self::DownLeft => write!(f, "DL"),
+            Self::DownRight => write!(f, "DR"),
+            Self::Vector(Position { x, y }) => write!(f, "{x},{y}"),
+        }
+    }
+}
+
+impl Direction {
+    const fn offset(self) -> Position {
+        let (x, y) = match self {
+            Self::Up => (0, 1),
+            Self::Down => (0, -1),
+            Self::Left => (-1, 0),
+            Self::Right => (1, 0),
+            Self::UpLeft => (-1, 1),
+            Self::UpRight => (1, 1),
+            Self::DownLeft => (-1, -1),
+            Self::DownRight => (1, -1),
+            Self::Vector(offset) => return offset,
+        };
+        Position { x, y }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 struct Move {
     direction: Direction,
     count: usize,
 }
 
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.direction, self.count)
+    }
+}
+
 impl FromStr for Move {
     type Err = anyhow::Error;
 
@@ -65,12 +129,9 @@ struct Position {
 
 impl Position {
     fn go(&mut self, d: Direction) {
-        match d {
-            Direction::Up => self.y += 1,
-            Direction::Down => self.y -= 1,
-            Direction::Left => self.x -= 1,
-            Direction::Right => self.x += 1,
-        }
+        let offset = d.offset();
+        self.x += offset.x;
+        self.y += offset.y;
     }
 
     const fn dist(&self, other: &Self) -> Self {
@@ -81,47 +142,147 @@ impl Position {
     }
 }
 
+/// How we measure the distance between two knots.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Metric {
+    // The puzzle's rule: diagonal neighbors are touching, and knots can
+    // move diagonally to catch up.
+    Chebyshev,
+    // Only knots in the same row or column can be touching, and knots
+    // only move horizontally or vertically.
+    Manhattan,
+}
+
+impl FromStr for Metric {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "chebyshev" => Self::Chebyshev,
+            "manhattan" => Self::Manhattan,
+            _ => bail!("Unknown metric '{s}'; expected 'chebyshev' or 'manhattan'"),
+        })
+    }
+}
+
+impl Metric {
+    const fn distance(self, Position { x, y }: Position) -> usize {
+        match self {
+            Self::Chebyshev => {
+                let (x, y) = (x.unsigned_abs(), y.unsigned_abs());
+                if x > y {
+                    x
+                } else {
+                    y
+                }
+            }
+            Self::Manhattan => x.unsigned_abs() + y.unsigned_abs(),
+        }
+    }
+
+    // A single step that takes a knot closer to a knot that is `offset`
+    // away.
+    const fn step(self, offset: Position) -> Position {
+        match self {
+            Self::Chebyshev => Position {
+                x: offset.x.signum(),
+                y: offset.y.signum(),
+            },
+            Self::Manhattan if offset.x.unsigned_abs() >= offset.y.unsigned_abs() => Position {
+                x: offset.x.signum(),
+                y: 0,
+            },
+            Self::Manhattan => Position {
+                x: 0,
+                y: offset.y.signum(),
+            },
+        }
+    }
+}
+
+/// How a knot follows the knot in front of it: if they're more than
+/// `max_slack` apart (using `metric`), the knot takes steps towards the
+/// one in front until they're close enough. The puzzle's rule is
+/// Chebyshev distance with a slack of 1, where a knot never needs more
+/// than one step, but a larger head move (or a tighter rope) can take
+/// several.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct FollowRule {
+    metric: Metric,
+    max_slack: usize,
+}
+
+impl Default for FollowRule {
+    fn default() -> Self {
+        Self {
+            metric: Metric::Chebyshev,
+            max_slack: 1,
+        }
+    }
+}
+
+impl FollowRule {
+    fn follow(self, leader: Position, mut follower: Position) -> Position {
+        loop {
+            let offset = leader.dist(&follower);
+            if self.metric.distance(offset) <= self.max_slack {
+                return follower;
+            }
+            follower.go(Direction::Vector(self.metric.step(offset)));
+        }
+    }
+}
+
 type Visited = HashSet<Position>;
 
-#[derive(Default, Debug)]
+/// A rope with any number of knots, where knot 0 is the head and the
+/// last knot is the tail. We keep track of the positions visited by
+/// every knot, not just the tail, since it's no more work.
+#[derive(Debug)]
 struct BridgeState {
-    knots: [Position; 10],
-    visited: Visited,
+    knots: Vec<Position>,
+    visited: Vec<Visited>,
+    rule: FollowRule,
 }
 
 impl BridgeState {
+    fn new(num_knots: usize) -> Result<Self> {
+        ensure!(num_knots > 0, "A rope needs at least one knot");
+        let start = Position::default();
+        Ok(Self {
+            knots: vec![start; num_knots],
+            visited: vec![HashSet::from([start]); num_knots],
+            rule: FollowRule::default(),
+        })
+    }
+
+    const fn with_rule(mut self, rule: FollowRule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    fn tail_visited(&self) -> &Visited {
+        // `new()` ensures there's always at least one knot.
+        &self.visited[self.visited.len() - 1]
+    }
+
     fn move_head(&mut self, d: Direction) {
         self.knots[0].go(d);
     }
 
     fn update_knot(&mut self, knot_to_update: usize) {
         let preceding_knot = self.knots[knot_to_update - 1];
-        let current_knot = &mut self.knots[knot_to_update];
-        match preceding_knot.dist(current_knot) {
-            Position { x, y } if x.abs() == 2 => {
-                current_knot.x += x.signum();
-                match y {
-                    -2 => current_knot.y -= 1,
-                    2 => current_knot.y += 1,
-                    _ => current_knot.y = preceding_knot.y,
-                }
-            }
-            Position { y, .. } if y.abs() == 2 => {
-                current_knot.x = preceding_knot.x;
-                current_knot.y += y.signum();
-            }
-            _ =>
-                /* Do nothing, the tail doesn't have to move */
-                {}
-        }
+        self.knots[knot_to_update] = self.rule.follow(preceding_knot, self.knots[knot_to_update]);
     }
 
     fn process_direction(&mut self, d: Direction) {
         self.move_head(d);
-        for i in 1..=9 {
+        for i in 1..self.knots.len() {
             self.update_knot(i);
         }
-        self.visited.insert(self.knots[9]);
+        for (knot, visited) in self.knots.iter().zip(&mut self.visited) {
+            visited.insert(*knot);
+        }
     }
 
     fn process_move(&mut self, m: &Move) {
@@ -131,6 +292,145 @@ impl BridgeState {
     fn process_moves(&mut self, moves: &[Move]) {
         moves.iter().for_each(|m| self.process_move(m));
     }
+
+    /// The smallest bounds that hold everywhere any knot has been.
+    fn bounds(&self) -> Bounds {
+        self.visited
+            .iter()
+            .flatten()
+            .fold(Bounds::new(Position::default()), Bounds::include)
+    }
+
+    /// Draw the rope the way the puzzle does, e.g.,
+    ///
+    /// ```text
+    /// ......
+    /// ......
+    /// ......
+    /// ......
+    /// 4321H.
+    /// ```
+    ///
+    /// The head is `H`, the other knots are numbered (with `T` for the
+    /// tail of a two knot rope, and `*` for knots past 9), and `s` is the
+    /// starting position. When several things are in the same place we
+    /// draw whichever is closest to the head.
+    fn render(&self, bounds: Bounds) -> String {
+        bounds.render(|position| {
+            self.knots
+                .iter()
+                .position(|knot| *knot == position)
+                .map(|i| self.knot_label(i))
+                .or_else(|| (position == Position::default()).then_some('s'))
+        })
+    }
+
+    /// Draw the positions visited by the given knot as `#`, with the
+    /// starting position as `s`.
+    fn render_visited(&self, knot: usize, bounds: Bounds) -> String {
+        bounds.render(|position| {
+            if position == Position::default() {
+                Some('s')
+            } else {
+                self.visited[knot].contains(&position).then_some('#')
+            }
+        })
+    }
+
+    fn knot_label(&self, knot: usize) -> char {
+        match knot {
+            0 => 'H',
+            1 if self.knots.len() == 2 => 'T',
+            // `from_digit()` only fails for knots past 9.
+            _ => u32::try_from(knot)
+                .ok()
+                .and_then(|knot| char::from_digit(knot, 10))
+                .unwrap_or('*'),
+        }
+    }
+}
+
+/// The (inclusive) region of the grid to draw.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Bounds {
+    min: Position,
+    max: Position,
+}
+
+impl Bounds {
+    const fn new(position: Position) -> Self {
+        Self {
+            min: position,
+            max: position,
+        }
+    }
+
+    fn include(self, position: &Position) -> Self {
+        Self {
+            min: Position {
+                x: self.min.x.min(position.x),
+                y: self.min.y.min(position.y),
+            },
+            max: Position {
+                x: self.max.x.max(position.x),
+                y: self.max.y.max(position.y),
+            },
+        }
+    }
+
+    // Draw each position with `label`, using `.` when it returns `None`.
+    // Up is positive y, so we draw the rows from the largest y down.
+    fn render(self, label: impl Fn(Position) -> Option<char>) -> String {
+        (self.min.y..=self.max.y)
+            .rev()
+            .map(|y| {
+                (self.min.x..=self.max.x)
+                    .map(|x| label(Position { x, y }).unwrap_or('.'))
+                    .chain(std::iter::once('\n'))
+                    .collect::<String>()
+            })
+            .collect()
+    }
+}
+
+static USAGE: &str =
+    "Usage: [--knots <n>] [--slack <n>] [--metric chebyshev|manhattan] [--frames] [--trails]";
+
+struct Options {
+    num_knots: usize,
+    rule: FollowRule,
+    frames: bool,
+    trails: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut options = Options {
+        num_knots: 10,
+        rule: FollowRule::default(),
+        frames: false,
+        trails: false,
+    };
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--knots" => {
+                let num_knots = args.next().context(USAGE)?;
+                options.num_knots = num_knots
+                    .parse()
+                    .with_context(|| format!("Couldn't parse the number of knots '{num_knots}'"))?;
+            }
+            "--slack" => {
+                let slack = args.next().context(USAGE)?;
+                options.rule.max_slack = slack
+                    .parse()
+                    .with_context(|| format!("Couldn't parse the slack '{slack}'"))?;
+            }
+            "--metric" => options.rule.metric = args.next().context(USAGE)?.parse()?,
+            "--frames" => options.frames = true,
+            "--trails" => options.trails = true,
+            _ => bail!("Unknown option '{flag}'\n{USAGE}"),
+        }
+    }
+    Ok(options)
 }
 
 fn main() -> Result<()> {
@@ -140,12 +440,38 @@ fn main() -> Result<()> {
         .map(str::parse)
         .collect::<Result<Vec<Move>>>()?;
 
-    let mut state = BridgeState::default();
+    let options = parse_args(env::args().skip(1))?;
+
+    let mut state = BridgeState::new(options.num_knots)?.with_rule(options.rule);
     state.process_moves(&moves);
-    let num_visited = state.visited.len();
+    let num_visited = state.tail_visited().len();
 
     println!("The number of visited positions was {num_visited}");
 
+    if options.frames {
+        // We run the simulation twice so every frame can use the same
+        // bounds, which we only know once we've seen the whole thing.
+        let bounds = state.bounds();
+        let mut replay = BridgeState::new(options.num_knots)?.with_rule(options.rule);
+        println!("== Initial State ==\n\n{}", replay.render(bounds));
+        for m in &moves {
+            replay.process_move(m);
+            println!("== {m} ==\n\n{}", replay.render(bounds));
+        }
+    }
+
+    if options.trails {
+        let bounds = state.bounds();
+        for (knot, visited) in state.visited.iter().enumerate() {
+            println!(
+                "Knot {} visited {} positions:\n\n{}",
+                state.knot_label(knot),
+                visited.len(),
+                state.render_visited(knot, bounds)
+            );
+        }
+    }
+
     Ok(())
 }
 
@@ -154,8 +480,8 @@ mod tests {
     use super::*;
 
     #[test]
-    fn write_up_example() {
-        let mut bridge_state = BridgeState::default();
+    fn write_up_example() -> Result<()> {
+        let mut bridge_state = BridgeState::new(10)?;
         let moves = [
             Move {
                 direction: Direction::Right,
@@ -168,5 +494,117 @@ mod tests {
         ];
         bridge_state.process_moves(&moves);
         println!("The final bridge state is {bridge_state:?}");
+        Ok(())
+    }
+
+    static SMALL_EXAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2";
+    static LARGER_EXAMPLE: &str = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20";
+
+    fn simulate(moves: &str, num_knots: usize) -> Result<BridgeState> {
+        let moves = moves
+            .lines()
+            .map(str::parse)
+            .collect::<Result<Vec<Move>>>()?;
+        let mut state = BridgeState::new(num_knots)?;
+        state.process_moves(&moves);
+        Ok(state)
+    }
+
+    #[test]
+    fn both_parts() -> Result<()> {
+        assert_eq!(13, simulate(SMALL_EXAMPLE, 2)?.tail_visited().len());
+        assert_eq!(1, simulate(SMALL_EXAMPLE, 10)?.tail_visited().len());
+        assert_eq!(36, simulate(LARGER_EXAMPLE, 10)?.tail_visited().len());
+        // Each knot only depends on the knots in front of it, so knot 1 of
+        // a long rope visits the same positions as the tail of a short one.
+        assert_eq!(
+            simulate(SMALL_EXAMPLE, 2)?.visited[1],
+            simulate(SMALL_EXAMPLE, 10)?.visited[1]
+        );
+        assert!(BridgeState::new(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn render() -> Result<()> {
+        let bounds = Bounds {
+            min: Position { x: 0, y: 0 },
+            max: Position { x: 5, y: 4 },
+        };
+        let state = simulate("R 4", 10)?;
+        assert_eq!(
+            "......\n......\n......\n......\n4321H.\n",
+            state.render(bounds)
+        );
+        let state = simulate("R 4\nU 4", 2)?;
+        assert_eq!(
+            "....H.\n....T.\n......\n......\ns.....\n",
+            state.render(bounds)
+        );
+        let state = simulate(SMALL_EXAMPLE, 2)?;
+        assert_eq!(bounds, state.bounds());
+        assert_eq!(
+            "..##..\n...##.\n.####.\n....#.\ns###..\n",
+            state.render_visited(1, bounds)
+        );
+        Ok(())
+    }
+
+    fn simulate_with(moves: &str, num_knots: usize, rule: FollowRule) -> Result<BridgeState> {
+        let moves = moves
+            .lines()
+            .map(str::parse)
+            .collect::<Result<Vec<Move>>>()?;
+        let mut state = BridgeState::new(num_knots)?.with_rule(rule);
+        state.process_moves(&moves);
+        Ok(state)
+    }
+
+    #[test]
+    fn standard_rule_on_input() -> Result<()> {
+        let moves = fs::read_to_string(INPUT_FILE)?;
+        assert_eq!(6464, simulate(&moves, 2)?.tail_visited().len());
+        assert_eq!(2604, simulate(&moves, 10)?.tail_visited().len());
+        Ok(())
+    }
+
+    #[test]
+    fn diagonal_and_vector_moves() -> Result<()> {
+        // A diagonal move is the same as the two moves it's made of, as far
+        // as where the head ends up.
+        let diagonal = simulate("UR 3\nDL 1", 2)?;
+        assert_eq!(Position { x: 2, y: 2 }, diagonal.knots[0]);
+        assert_eq!(Position { x: 2, y: 2 }, diagonal.knots[1]);
+        // With a jump, the tail takes several steps to catch up, but its
+        // trail only records where it ends up after each step of the head.
+        let jump = simulate("5,-2 1", 2)?;
+        assert_eq!(Position { x: 4, y: -2 }, jump.knots[1]);
+        assert_eq!(2, jump.tail_visited().len());
+        assert_eq!("3,-2 4", "3,-2 4".parse::<Move>()?.to_string());
+        assert!("X 4".parse::<Move>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn other_rules() -> Result<()> {
+        let slack = FollowRule {
+            metric: Metric::Chebyshev,
+            max_slack: 2,
+        };
+        let state = simulate_with("R 4", 2, slack)?;
+        assert_eq!(Position { x: 2, y: 0 }, state.knots[1]);
+
+        let manhattan = FollowRule {
+            metric: Metric::Manhattan,
+            max_slack: 1,
+        };
+        // A diagonal neighbor isn't touching with Manhattan distance, so
+        // the tail has to move, one step at a time.
+        let state = simulate_with("UR 1", 2, manhattan)?;
+        assert_eq!(Position { x: 1, y: 0 }, state.knots[1]);
+        // Ties between the axes are broken by moving horizontally.
+        let state = simulate_with("U 1\nR 2", 2, manhattan)?;
+        assert_eq!(Position { x: 2, y: 0 }, state.knots[1]);
+        Ok(())
     }
 }
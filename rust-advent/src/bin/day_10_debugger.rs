@@ -0,0 +1,389 @@
+#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+#![warn(clippy::unwrap_used)]
+#![warn(clippy::expect_used)]
+
+use anyhow::{bail, Context, Result};
+use rust_advent::cpu::{parse_program, Instruction, Screen, Vm, NUM_PIXELS, X};
+use std::{
+    env,
+    fmt::Display,
+    fs::{self},
+    io::{self, Write},
+    str::FromStr,
+};
+
+// An interactive debugger for the day 10 CPU. It loads a program and lets
+// you step through it a cycle at a time, run to a given cycle, set
+// breakpoints on the value of `x`, and rewind, printing the registers and
+// the (partially drawn) CRT along the way.
+//
+// Run it with `cargo run --bin day_10_debugger -- [<program file>]`; the
+// program defaults to the day 10 input. Type `help` at the prompt for the
+// list of commands.
+
+static INPUT_FILE: &str = "../inputs/day_10.input";
+
+// What we step through (and rewind): the shared day 10 CPU, along with
+// the CRT it's drawing on. Cloning the `Vm` doesn't copy the program, so
+// keeping a clone for every cycle is cheap.
+#[derive(Debug, Clone)]
+struct Machine {
+    vm: Vm,
+    screen: Screen,
+}
+
+impl Machine {
+    fn new(program: Vec<Instruction>) -> Self {
+        Self {
+            vm: Vm::new(program),
+            screen: Screen::default(),
+        }
+    }
+
+    const fn x(&self) -> isize {
+        self.vm.registers().get(X)
+    }
+
+    const fn is_finished(&self) -> bool {
+        self.vm.cycle() > NUM_PIXELS
+    }
+
+    // The signal strength _during_ the current cycle, as in part 1.
+    #[allow(clippy::cast_possible_wrap)]
+    const fn signal_strength(&self) -> isize {
+        self.vm.cycle() as isize * self.x()
+    }
+
+    fn tick(&mut self) {
+        self.vm.tick(&mut [&mut self.screen]);
+    }
+}
+
+impl Display for Machine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "cycle: {}  {}  signal strength: {}",
+            self.vm.cycle(),
+            self.vm.registers(),
+            self.signal_strength()
+        )?;
+        match self.vm.current_instruction() {
+            Some(instruction) => write!(
+                f,
+                "pc: {}  instruction: {instruction} ({} cycle(s) remaining)",
+                self.vm.program_counter(),
+                self.vm.remaining_cycles()
+            ),
+            None => write!(f, "pc: {}  halted", self.vm.program_counter()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Step(usize),
+    RunUntil(usize),
+    BreakWhenX(isize),
+    ClearBreakpoints,
+    Continue,
+    Back(usize),
+    Registers,
+    Crt,
+    Load(String),
+    Help,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let count = |s: Option<&&str>| -> Result<usize> {
+            s.map_or(Ok(1), |s| {
+                s.parse::<usize>()
+                    .with_context(|| format!("Couldn't parse '{s}' to a count"))
+            })
+        };
+        Ok(match parts.as_slice() {
+            ["step" | "s", rest @ ..] if rest.len() <= 1 => Self::Step(count(rest.first())?),
+            ["run" | "r", cycle] => Self::RunUntil(
+                cycle
+                    .parse()
+                    .with_context(|| format!("Couldn't parse '{cycle}' to a cycle"))?,
+            ),
+            ["break" | "b", "x", "==", value] => Self::BreakWhenX(
+                value
+                    .parse()
+                    .with_context(|| format!("Couldn't parse '{value}' to a value for x"))?,
+            ),
+            ["clear"] => Self::ClearBreakpoints,
+            ["continue" | "c"] => Self::Continue,
+            ["back" | "rewind", rest @ ..] if rest.len() <= 1 => Self::Back(count(rest.first())?),
+            ["regs" | "registers"] => Self::Registers,
+            ["crt" | "screen"] => Self::Crt,
+            ["load", file] => Self::Load((*file).to_string()),
+            ["help" | "h" | "?"] => Self::Help,
+            ["quit" | "q" | "exit"] => Self::Quit,
+            _ => bail!("Unknown command '{line}'; type `help` for the list of commands"),
+        })
+    }
+}
+
+static HELP: &str = "\
+step [n]        advance n cycles (default 1)
+run <cycle>     run until the start of the given cycle
+break x == <v>  stop `continue` whenever x is v
+clear           remove all the breakpoints
+continue        run until a breakpoint is hit or the CRT is fully drawn
+back [n]        rewind n cycles (default 1)
+regs            print the registers
+crt             print the CRT as drawn so far
+load <file>     load a new program and start over
+quit            leave the debugger";
+
+struct Debugger {
+    machine: Machine,
+    // Every earlier state of the CPU, so we can rewind. There are only
+    // 240 cycles, so keeping all of them is cheap.
+    history: Vec<Machine>,
+    breakpoints: Vec<isize>,
+}
+
+impl Debugger {
+    fn new(program: Vec<Instruction>) -> Self {
+        Self {
+            machine: Machine::new(program),
+            history: Vec::new(),
+            breakpoints: Vec::new(),
+        }
+    }
+
+    fn tick(&mut self) -> Result<()> {
+        if self.machine.is_finished() {
+            bail!("The CRT is fully drawn; there are no more cycles to run");
+        }
+        self.history.push(self.machine.clone());
+        self.machine.tick();
+        Ok(())
+    }
+
+    fn step(&mut self, num_cycles: usize) -> Result<()> {
+        for _ in 0..num_cycles {
+            self.tick()?;
+        }
+        Ok(())
+    }
+
+    fn run_until(&mut self, target_clock_cycle: usize) -> Result<()> {
+        if target_clock_cycle < self.machine.vm.cycle() {
+            bail!(
+                "We're already at cycle {}; use `back` to rewind",
+                self.machine.vm.cycle()
+            );
+        }
+        while self.machine.vm.cycle() < target_clock_cycle {
+            self.tick()?;
+        }
+        Ok(())
+    }
+
+    // Returns the value of `x` that stopped us, or `None` if we ran off
+    // the end without hitting a breakpoint. We always run at least one
+    // cycle, so continuing from a breakpoint makes progress, but then stop
+    // after _any_ cycle where `x` is a breakpoint value, even if it
+    // already had that value.
+    fn continue_to_breakpoint(&mut self) -> Result<Option<isize>> {
+        loop {
+            self.tick()?;
+            if self.breakpoints.contains(&self.machine.x()) {
+                return Ok(Some(self.machine.x()));
+            }
+            if self.machine.is_finished() {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn back(&mut self, num_cycles: usize) -> Result<()> {
+        if num_cycles > self.history.len() {
+            bail!(
+                "We can only rewind {} cycle(s) from here",
+                self.history.len()
+            );
+        }
+        let new_len = self.history.len() - num_cycles;
+        // `split_off` leaves the states we're keeping in `history` and
+        // hands back the rest, the first of which is where we rewind to.
+        let mut rewound = self.history.split_off(new_len);
+        self.machine = rewound.swap_remove(0);
+        Ok(())
+    }
+
+    // Returns `false` when it's time to quit.
+    fn execute(&mut self, command: Command) -> Result<bool> {
+        match command {
+            Command::Step(num_cycles) => {
+                self.step(num_cycles)?;
+                println!("{}", self.machine);
+            }
+            Command::RunUntil(cycle) => {
+                self.run_until(cycle)?;
+                println!("{}", self.machine);
+            }
+            Command::BreakWhenX(value) => {
+                self.breakpoints.push(value);
+                println!("Breakpoints on x == {:?}", self.breakpoints);
+            }
+            Command::ClearBreakpoints => {
+                self.breakpoints.clear();
+                println!("Cleared all breakpoints");
+            }
+            Command::Continue => {
+                match self.continue_to_breakpoint()? {
+                    Some(value) => println!("Hit breakpoint x == {value}"),
+                    None => println!("Ran to the end without hitting a breakpoint"),
+                }
+                println!("{}", self.machine);
+            }
+            Command::Back(num_cycles) => {
+                self.back(num_cycles)?;
+                println!("{}", self.machine);
+            }
+            Command::Registers => println!("{}", self.machine),
+            Command::Crt => print!("{}", self.machine.screen),
+            Command::Load(file) => {
+                let breakpoints = std::mem::take(&mut self.breakpoints);
+                *self = Self::new(load_program(&file)?);
+                self.breakpoints = breakpoints;
+                println!(
+                    "Loaded {} instructions from '{file}'",
+                    self.machine.vm.program_len()
+                );
+            }
+            Command::Help => println!("{HELP}"),
+            Command::Quit => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+fn load_program(file: &str) -> Result<Vec<Instruction>> {
+    let contents =
+        fs::read_to_string(file).with_context(|| format!("Failed to open file '{file}'"))?;
+    parse_program(&contents)
+}
+
+fn main() -> Result<()> {
+    let input_file = env::args().nth(1).unwrap_or_else(|| INPUT_FILE.to_string());
+    let mut debugger = Debugger::new(load_program(&input_file)?);
+
+    println!(
+        "Loaded {} instructions from '{input_file}'; type `help` for the list of commands",
+        debugger.machine.vm.program_len()
+    );
+    println!("{}", debugger.machine);
+
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("(day 10) ");
+        io::stdout().flush()?;
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Errors from bad commands (or trying to step past the end) are
+        // just reported; they shouldn't end the debugging session.
+        match line.parse().and_then(|command| debugger.execute(command)) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("Error: {e:#}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT_FILE: &str = "../inputs/day_10_test.input";
+
+    #[test]
+    fn parse_commands() -> Result<()> {
+        assert_eq!(Command::Step(1), "step".parse()?);
+        assert_eq!(Command::Step(5), "s 5".parse()?);
+        assert_eq!(Command::RunUntil(20), "run 20".parse()?);
+        assert_eq!(Command::BreakWhenX(-3), "break x == -3".parse()?);
+        assert_eq!(Command::Back(2), "back 2".parse()?);
+        assert!("break x = 3".parse::<Command>().is_err());
+        assert!("fly".parse::<Command>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn signal_strengths() -> Result<()> {
+        let mut debugger = Debugger::new(load_program(TEST_INPUT_FILE)?);
+        let mut total_signal_strength = 0;
+        for target in (20..=220).step_by(40) {
+            debugger.run_until(target)?;
+            total_signal_strength += debugger.machine.signal_strength();
+        }
+        assert_eq!(13_140, total_signal_strength);
+        Ok(())
+    }
+
+    #[test]
+    fn rewind() -> Result<()> {
+        let mut debugger = Debugger::new(load_program(TEST_INPUT_FILE)?);
+        debugger.run_until(20)?;
+        let x_at_20 = debugger.machine.x();
+        debugger.step(40)?;
+        debugger.back(40)?;
+        assert_eq!(20, debugger.machine.vm.cycle());
+        assert_eq!(x_at_20, debugger.machine.x());
+        assert_eq!(' ', debugger.machine.screen.rows[0][19]);
+        assert!(debugger.back(20).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn breakpoint() -> Result<()> {
+        let mut debugger = Debugger::new(load_program(TEST_INPUT_FILE)?);
+        debugger.breakpoints.push(16);
+        assert_eq!(Some(16), debugger.continue_to_breakpoint()?);
+        assert_eq!(3, debugger.machine.vm.cycle());
+        Ok(())
+    }
+
+    #[test]
+    fn continue_from_breakpoint() -> Result<()> {
+        let mut debugger = Debugger::new(load_program(TEST_INPUT_FILE)?);
+        debugger.breakpoints.extend([16, 21]);
+        assert_eq!(Some(16), debugger.continue_to_breakpoint()?);
+        assert_eq!(3, debugger.machine.vm.cycle());
+        // `x` is still 16 during cycle 4, so we stop again right away.
+        assert_eq!(Some(16), debugger.continue_to_breakpoint()?);
+        assert_eq!(4, debugger.machine.vm.cycle());
+        assert_eq!(Some(21), debugger.continue_to_breakpoint()?);
+        assert_eq!(19, debugger.machine.vm.cycle());
+        Ok(())
+    }
+
+    #[test]
+    fn breakpoint_on_starting_value() -> Result<()> {
+        let mut debugger = Debugger::new(load_program(TEST_INPUT_FILE)?);
+        debugger.breakpoints.push(1);
+        assert_eq!(Some(1), debugger.continue_to_breakpoint()?);
+        assert_eq!(2, debugger.machine.vm.cycle());
+        Ok(())
+    }
+}
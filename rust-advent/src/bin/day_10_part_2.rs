@@ -3,143 +3,214 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{Context, Result};
-use std::{
-    fmt::Display,
-    fs::{self},
-    str::FromStr,
+use anyhow::{ensure, Context, Result};
+use itertools::Itertools;
+use rust_advent::cpu::{
+    parse_program, Instruction, Observer, Registers, Screen, Vm, NUM_PIXELS, NUM_SCREEN_COLS,
+    NUM_SCREEN_ROWS, X,
 };
+use std::fs::{self};
 
 static INPUT_FILE: &str = "../inputs/day_10.input";
 
-#[derive(Default, Debug)]
-enum Instruction {
-    #[default]
-    Noop,
-    AddX(isize),
+/// Part 1: the sum of the signal strengths (the cycle number times `x`)
+/// during the sampled cycles.
+#[derive(Debug)]
+struct SignalStrength {
+    first_sample: usize,
+    sample_interval: usize,
+    total: isize,
 }
 
-impl Instruction {
-    const fn cycles(&self) -> usize {
-        match self {
-            Self::Noop => 1,
-            Self::AddX(_) => 2,
+impl SignalStrength {
+    const fn new(first_sample: usize, sample_interval: usize) -> Self {
+        Self {
+            first_sample,
+            sample_interval,
+            total: 0,
         }
     }
 }
 
-impl FromStr for Instruction {
-    type Err = anyhow::Error;
-
-    fn from_str(line: &str) -> Result<Self> {
-        if line == "noop" {
-            return Ok(Self::Noop);
+impl Observer for SignalStrength {
+    // Cycle numbers are small enough that they'll never wrap, and
+    // `is_multiple_of` would need Rust 1.87.
+    #[allow(clippy::cast_possible_wrap, clippy::manual_is_multiple_of)]
+    fn observe(&mut self, cycle: usize, registers: &Registers) {
+        if cycle >= self.first_sample && (cycle - self.first_sample) % self.sample_interval == 0 {
+            self.total += cycle as isize * registers.get(X);
         }
-        let value = line
-            .split_ascii_whitespace()
-            .nth(1)
-            .with_context(|| "No value field in line '{line}'.")?
-            .parse::<isize>()?;
-        Ok(Self::AddX(value))
     }
 }
 
-const NUM_SCREEN_ROWS: usize = 6;
-const NUM_SCREEN_COLS: usize = 40;
-
-#[derive(Debug)]
-struct Screen {
-    rows: [[char; NUM_SCREEN_COLS]; NUM_SCREEN_ROWS],
+// Each letter on the CRT is 4 pixels wide, with a blank column between
+// letters, so the 40 columns hold 8 letters.
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_SPACING: usize = GLYPH_WIDTH + 1;
+
+type Glyph = [[char; GLYPH_WIDTH]; NUM_SCREEN_ROWS];
+
+/// The letters that show up in Advent of Code answers, drawn the way
+/// they appear on the CRT. Not every letter shows up (some, like `M`
+/// and `W`, wouldn't fit in 4 columns), so this only has the ones that
+/// have been seen in puzzle answers.
+static FONT: [(char, [&str; NUM_SCREEN_ROWS]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+fn recognize(glyph: &Glyph) -> Option<char> {
+    FONT.iter()
+        .find(|(_, rows)| {
+            rows.iter()
+                .zip(glyph)
+                .all(|(font_row, glyph_row)| font_row.chars().eq(glyph_row.iter().copied()))
+        })
+        .map(|(letter, _)| *letter)
 }
 
-impl Default for Screen {
-    fn default() -> Self {
-        Self {
-            rows: [[' '; NUM_SCREEN_COLS]; NUM_SCREEN_ROWS],
+fn glyphs(screen: &Screen) -> impl Iterator<Item = Glyph> + '_ {
+    (0..NUM_SCREEN_COLS / GLYPH_SPACING).map(|i| {
+        let start = i * GLYPH_SPACING;
+        let mut glyph = [[' '; GLYPH_WIDTH]; NUM_SCREEN_ROWS];
+        for (glyph_row, row) in glyph.iter_mut().zip(&screen.rows) {
+            glyph_row.copy_from_slice(&row[start..start + GLYPH_WIDTH]);
         }
-    }
+        glyph
+    })
 }
 
-impl Display for Screen {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for row in self.rows {
-            for c in row {
-                write!(f, "{c}")?;
-            }
-            writeln!(f)?;
+/// Read the letters off the screen, so we don't have to squint at
+/// it. The error lists every glyph we couldn't recognize, along with
+/// where it was on the screen.
+fn read_letters(screen: &Screen) -> Result<String> {
+    let mut letters = String::new();
+    let mut unrecognized = Vec::new();
+    for (i, glyph) in glyphs(screen).enumerate() {
+        if let Some(letter) = recognize(&glyph) {
+            letters.push(letter);
+        } else {
+            letters.push('?');
+            let drawing = glyph
+                .iter()
+                .map(|row| row.iter().collect::<String>())
+                .join("\n");
+            unrecognized.push(format!(
+                "glyph {} (columns {}..{}):\n{drawing}",
+                i + 1,
+                i * GLYPH_SPACING,
+                i * GLYPH_SPACING + GLYPH_WIDTH
+            ));
         }
-        Ok(())
     }
+    ensure!(
+        unrecognized.is_empty(),
+        "Couldn't read the letters '{letters}' on the screen; the unrecognized glyphs were\n{}",
+        unrecognized.join("\n")
+    );
+    Ok(letters)
 }
 
-#[derive(Default, Debug)]
-struct Cpu {
-    x: isize,
-    program: Vec<Instruction>,
-    current_instruction: Instruction,
-    current_clock_cycle: usize,
-    current_instruction_remaining_cycles: usize,
-    screen: Screen,
+fn run_program(program: &[Instruction]) -> (isize, Screen) {
+    let mut signal_strength = SignalStrength::new(20, 40);
+    let mut screen = Screen::default();
+    let mut vm = Vm::new(program);
+    vm.run(&mut [&mut signal_strength, &mut screen], NUM_PIXELS);
+    (signal_strength.total, screen)
 }
 
-impl Cpu {
-    fn new(mut program: Vec<Instruction>) -> Self {
-        program.reverse();
-        let mut cpu = Self {
-            x: 1,
-            program,
-            current_clock_cycle: 1,
-            ..Default::default()
-        };
-        cpu.load_instruction();
-        cpu
-    }
+fn main() -> Result<()> {
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+    let program = parse_program(&contents)?;
+    let (total_signal_strength, screen) = run_program(&program);
+    println!("The total signal strength was {total_signal_strength}");
+    println!("{screen}");
+    println!("The letters on the screen were {}", read_letters(&screen)?);
 
-    fn load_instruction(&mut self) {
-        // The `unwrap_or` says the next instruction is a `Noop` just in case we run past
-        // the end of the program.
-        self.current_instruction = self.program.pop().unwrap_or(Instruction::Noop);
-        self.current_instruction_remaining_cycles = self.current_instruction.cycles();
-    }
+    Ok(())
+}
 
-    // This is a cheap hack, but I really don't want to "properly" deal with the cast at the moment.
-    #[allow(clippy::cast_possible_wrap)]
-    fn draw(&mut self, row: usize, col: usize) {
-        self.screen.rows[row][col] = if (self.x - 1..=self.x + 1).contains(&(col as isize)) {
-            '#'
-        } else {
-            '.'
-        };
+#[cfg(test)]
+mod signal_strength_tests {
+    use super::*;
+
+    static TEST_INPUT_FILE: &str = "../inputs/day_10_test.input";
+
+    #[test]
+    fn larger_example() -> Result<()> {
+        let program = parse_program(&fs::read_to_string(TEST_INPUT_FILE)?)?;
+        let (total_signal_strength, screen) = run_program(&program);
+        assert_eq!(13140, total_signal_strength);
+        assert_eq!(
+            "##..##..##..##..##..##..##..##..##..##..",
+            screen.rows[0].iter().collect::<String>()
+        );
+        assert_eq!(
+            "#######.......#######.......#######.....",
+            screen.rows[5].iter().collect::<String>()
+        );
+        Ok(())
     }
 
-    fn tick(&mut self, row: usize, col: usize) {
-        self.current_clock_cycle += 1;
-        self.current_instruction_remaining_cycles -= 1;
-        self.draw(row, col);
-        if self.current_instruction_remaining_cycles == 0 {
-            match self.current_instruction {
-                Instruction::Noop => {}
-                Instruction::AddX(val_to_add) => self.x += val_to_add,
-            }
-            self.load_instruction();
-        }
+    #[test]
+    fn idles_after_halting() -> Result<()> {
+        let program = parse_program("addx 3")?;
+        let mut signal_strength = SignalStrength::new(2, 3);
+        let mut vm = Vm::new(program);
+        vm.run(&mut [&mut signal_strength], 10);
+        assert_eq!(11, vm.cycle());
+        // Cycle 2 sees `x=1`, then cycles 5 and 8 see the final `x=4`.
+        assert_eq!(2 + 5 * 4 + 8 * 4, signal_strength.total);
+        Ok(())
     }
 }
 
-fn main() -> Result<()> {
-    let instructions = fs::read_to_string(INPUT_FILE)
-        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?
-        .lines()
-        .map(Instruction::from_str)
-        .collect::<Result<Vec<_>>>()?;
-
-    let mut cpu: Cpu = Cpu::new(instructions);
-    for row in 0..NUM_SCREEN_ROWS {
-        for col in 0..NUM_SCREEN_COLS {
-            cpu.tick(row, col);
-        }
+#[cfg(test)]
+mod ocr_tests {
+    use super::*;
+
+    #[test]
+    fn read_input_letters() -> Result<()> {
+        let program = parse_program(&fs::read_to_string(INPUT_FILE)?)?;
+        let (_, screen) = run_program(&program);
+        assert_eq!("RUAKHBEK", read_letters(&screen)?);
+        Ok(())
     }
-    println!("{}", cpu.screen);
 
-    Ok(())
+    #[test]
+    fn unrecognized_glyphs() {
+        let mut screen = Screen::default();
+        for (row, letter_row) in screen.rows.iter_mut().zip(FONT[0].1) {
+            row.fill('.');
+            for (i, c) in letter_row.chars().enumerate() {
+                row[i] = c;
+                row[2 * GLYPH_SPACING + i] = c;
+            }
+        }
+        // Take a bite out of the crossbar of the second `A`.
+        screen.rows[3][2 * GLYPH_SPACING + 1] = '.';
+        let error = read_letters(&screen).unwrap_or_else(|e| e.to_string());
+        assert!(error.contains("'A???????'"), "{error}");
+        assert!(error.contains("glyph 2 (columns 5..9)"), "{error}");
+        assert!(
+            error.contains("glyph 3 (columns 10..14):\n.##.\n#..#\n#..#\n#.##\n"),
+            "{error}"
+        );
+    }
 }
@@ -3,42 +3,97 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
 use std::{
     cmp::Reverse,
-    collections::{BinaryHeap, HashMap},
+    collections::BinaryHeap,
+    env,
     fs::{self},
     str::FromStr,
 };
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+static STANDARD_ELEVATIONS: &str = "abcdefghijklmnopqrstuvwxyz";
+
+/// The characters used for the heights, from lowest to highest. The start
+/// (`S`) is at the lowest height and the end (`E`) is at the highest.
 #[derive(Debug)]
-enum Height {
-    Start,
-    End,
-    Height(u8),
-}
-
-impl Height {
-    const fn new(c: char) -> Self {
-        match c {
-            'S' => Self::Start, // Start has height 0
-            'E' => Self::End,   // End has height 26
-            _ => Self::Height(c as u8 - b'a'),
+struct Elevations {
+    levels: Vec<char>,
+}
+
+impl Elevations {
+    fn new(levels: &str) -> Result<Self> {
+        let levels = levels.chars().collect::<Vec<_>>();
+        ensure!(
+            (1..=usize::from(u8::MAX) + 1).contains(&levels.len()),
+            "There must be between 1 and 256 elevations, but there were {}",
+            levels.len()
+        );
+        if let Some(c) = levels.iter().find(|c| ['S', 'E'].contains(c)) {
+            bail!("'{c}' marks the start or end, so it can't be an elevation");
         }
+        if let Some(duplicate) = levels.iter().duplicates().next() {
+            bail!("The elevation '{duplicate}' appears more than once");
+        }
+        Ok(Self { levels })
     }
 
-    const fn get_height(&self) -> u8 {
-        match self {
-            Self::Start => 0,
-            Self::End => 25,
-            Self::Height(h) => *h,
+    fn height(&self, c: char) -> Option<u8> {
+        let height = self.levels.iter().position(|&level| level == c)?;
+        u8::try_from(height).ok()
+    }
+
+    // A `const` `Vec::len` would need Rust 1.87.
+    #[allow(clippy::missing_const_for_fn)]
+    fn highest(&self) -> u8 {
+        // `new` makes sure there are at most 256 levels.
+        #[allow(clippy::cast_possible_truncation)]
+        let highest = (self.levels.len() - 1) as u8;
+        highest
+    }
+}
+
+/// How much a hiker can climb or descend in one step, and what each step
+/// costs. The puzzle's rule is the default: at most one step up, any
+/// distance down, and every step costs 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ClimbRule {
+    max_ascent: u8,
+    max_descent: Option<u8>,
+    // Each step costs 1, plus this much for each unit of height change, so
+    // routes that go up and down a lot cost more.
+    height_cost: u32,
+}
+
+impl Default for ClimbRule {
+    fn default() -> Self {
+        Self {
+            max_ascent: 1,
+            max_descent: None,
+            height_cost: 0,
         }
     }
 }
 
-#[derive(EnumIter)]
+impl ClimbRule {
+    // The cost of stepping from height `from` to height `to`, or `None`
+    // if we can't make that step. The costs are `u64`s so that even the
+    // biggest `height_cost` times the biggest height change can't
+    // overflow.
+    fn cost(self, from: u8, to: u8) -> Option<u64> {
+        let allowed = if to >= from {
+            to - from <= self.max_ascent
+        } else {
+            !matches!(self.max_descent, Some(max) if from - to > max)
+        };
+        allowed.then(|| 1 + u64::from(self.height_cost) * u64::from(from.abs_diff(to)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, EnumIter)]
 enum Direction {
     Up,
     Down,
@@ -46,6 +101,18 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    // How the puzzle draws a step in this direction on the hike map.
+    const fn arrow(self) -> char {
+        match self {
+            Self::Up => '^',
+            Self::Down => 'v',
+            Self::Left => '<',
+            Self::Right => '>',
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 struct Location {
     row: usize,
@@ -53,123 +120,476 @@ struct Location {
 }
 
 impl Location {
-    fn neighbor(&self, direction: &Direction) -> Option<Self> {
+    fn neighbor(&self, direction: Direction) -> Option<Self> {
         let mut row = self.row;
         let mut col = self.col;
         match direction {
-            Direction::Up => col = col.checked_sub(1)?,
-            Direction::Down => col += 1,
-            Direction::Left => row = row.checked_sub(1)?,
-            Direction::Right => row += 1,
+            Direction::Up => row = row.checked_sub(1)?,
+            Direction::Down => row += 1,
+            Direction::Left => col = col.checked_sub(1)?,
+            Direction::Right => col += 1,
         }
         Some(Self { row, col })
     }
 }
 
 struct Terrain {
-    heights: Vec<Vec<Height>>,
+    heights: Vec<Vec<u8>>,
+    start: Location,
+    end: Location,
+    rule: ClimbRule,
 }
 
 impl FromStr for Terrain {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        let mut heights: Vec<Vec<Height>> = Vec::new();
-        for line in s.lines() {
-            let mut row_heights: Vec<Height> = Vec::with_capacity(line.len());
-            for c in line.chars() {
-                let height = Height::new(c);
+        Self::parse(s, &Elevations::new(STANDARD_ELEVATIONS)?)
+    }
+}
+
+impl Terrain {
+    fn parse(s: &str, elevations: &Elevations) -> Result<Self> {
+        let mut heights: Vec<Vec<u8>> = Vec::new();
+        let mut start: Option<Location> = None;
+        let mut end: Option<Location> = None;
+        for (row, line) in s.lines().enumerate() {
+            let mut row_heights: Vec<u8> = Vec::with_capacity(line.len());
+            for (col, c) in line.chars().enumerate() {
+                let (marker, height) = match c {
+                    'S' => (&mut start, 0),
+                    'E' => (&mut end, elevations.highest()),
+                    _ => {
+                        let height = elevations.height(c).with_context(|| {
+                            format!(
+                                "Unknown elevation '{c}' on line {}, column {}",
+                                row + 1,
+                                col + 1
+                            )
+                        })?;
+                        row_heights.push(height);
+                        continue;
+                    }
+                };
+                ensure!(
+                    marker.is_none(),
+                    "There was more than one '{c}' in the terrain"
+                );
+                *marker = Some(Location { row, col });
                 row_heights.push(height);
             }
             heights.push(row_heights);
         }
-        Ok(Self { heights })
+        let start = start.context("We never found the start location")?;
+        let end = end.context("We never found the end location")?;
+        Ok(Self {
+            heights,
+            start,
+            end,
+            rule: ClimbRule::default(),
+        })
+    }
+
+    const fn with_rule(mut self, rule: ClimbRule) -> Self {
+        self.rule = rule;
+        self
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
 struct Node {
-    dist: u32,
+    dist: u64,
     location: Location,
 }
 
+/// The length of the shortest path from every location to the end, or
+/// `None` for locations that can't reach the end at all.
+struct DistanceField {
+    distances: Vec<Vec<Option<u64>>>,
+}
+
+impl DistanceField {
+    fn distance(&self, location: &Location) -> Option<u64> {
+        self.distances
+            .get(location.row)
+            .and_then(|row| row.get(location.col))
+            .copied()
+            .flatten()
+    }
+}
+
+/// A hike from its first location to the end, one step at a time.
+struct Path {
+    locations: Vec<Location>,
+}
+
+impl Path {
+    // A `const` `Vec::len` would need Rust 1.87.
+    #[allow(clippy::missing_const_for_fn)]
+    fn len(&self) -> usize {
+        self.locations.len().saturating_sub(1)
+    }
+
+    // A path always has at least its starting location.
+    fn start(&self) -> &Location {
+        &self.locations[0]
+    }
+}
+
 impl Terrain {
     fn get_height(&self, location: &Location) -> Option<u8> {
         self.heights
             .get(location.row)
             .and_then(|row| row.get(location.col))
-            .map(Height::get_height)
+            .copied()
     }
 
-    fn accessible_locations<'a>(
+    fn neighbors<'a>(
         &'a self,
-        node: &'a Node,
-        current_height: u8,
-    ) -> impl Iterator<Item = Location> + 'a {
+        location: &'a Location,
+    ) -> impl Iterator<Item = (Direction, Location)> + 'a {
         Direction::iter()
-            .filter_map(|direction| node.location.neighbor(&direction))
-            .map(|location| (self.get_height(&location), location))
-            .filter_map(move |(ht, location)| {
-                ht.and_then(|ht| (ht <= current_height + 1).then_some(location))
-            })
+            .filter_map(|direction| Some((direction, location.neighbor(direction)?)))
+            .filter(|(_, neighbor)| self.get_height(neighbor).is_some())
     }
 
-    fn shortest_path_length(&self, location: Location) -> Result<u32> {
-        // This HashMap maps `Location`s to `u32`, keeping track of the shortest known
-        //   paths to given locations.
-        let mut best_distance = HashMap::new();
+    // The cost of stepping from `from` to the neighboring `to`, or `None`
+    // if the climb rule doesn't let us make that step.
+    fn step_cost(&self, from: &Location, to: &Location) -> Option<u64> {
+        self.rule.cost(self.get_height(from)?, self.get_height(to)?)
+    }
+
+    /// Search backwards from the end, so that one search tells us how far
+    /// every location is from the end. That answers "how far is it from
+    /// the start" and "how far is it from the closest `a`" at the same
+    /// time.
+    fn distances_to_end(&self) -> DistanceField {
+        let mut distances = self
+            .heights
+            .iter()
+            .map(|row| vec![None; row.len()])
+            .collect::<Vec<_>>();
 
         let mut open_list: BinaryHeap<Reverse<Node>> = BinaryHeap::new();
-        open_list.push(Reverse(Node { location, dist: 0 }));
+        open_list.push(Reverse(Node {
+            location: self.end.clone(),
+            dist: 0,
+        }));
 
         while let Some(Reverse(node)) = open_list.pop() {
-            let height = &self.heights[node.location.row][node.location.col];
-
-            if matches!(height, Height::End) {
-                return Ok(node.dist);
+            let best = &mut distances[node.location.row][node.location.col];
+            if best.is_some_and(|dist| dist <= node.dist) {
+                continue;
             }
+            *best = Some(node.dist);
 
-            let bd = best_distance.get(&node.location).copied();
-            // println!("Processing {node:?} with height {height:?} and best distance {bd:?}");
-            if let Some(dist) = bd {
-                if dist <= node.dist {
+            // Since we're going backwards, these are the locations we
+            // could have come from to get to `node`.
+            for (_, previous) in self.neighbors(&node.location) {
+                // A route whose cost doesn't fit in a `u64` can't be the
+                // cheapest one to anywhere we could report, so we just
+                // drop it rather than giving up on the whole search.
+                let Some(dist) = self
+                    .step_cost(&previous, &node.location)
+                    .and_then(|cost| node.dist.checked_add(cost))
+                else {
                     continue;
-                }
-            }
-
-            best_distance.insert(node.location.clone(), node.dist);
-            let accessible = self.accessible_locations(&node, height.get_height());
-            // I could replace this `for` loop with a `for_each()` call.
-            // That would probably make the most sense if I brought the body
-            // of `self.accessible_locations` back to this function, and
-            // I'm not sure I'm jazzed about that.
-            for location in accessible {
-                let new_node = Node {
-                    location,
-                    dist: node.dist + 1,
                 };
-                // println!("\tPushing node {new_node:?}");
-                open_list.push(Reverse(new_node));
+                open_list.push(Reverse(Node {
+                    location: previous,
+                    dist,
+                }));
             }
         }
 
-        bail!("We failed to find the end location!")
+        DistanceField { distances }
+    }
+
+    /// Follow the distance field downhill (so to speak) from `start` to
+    /// the end, or `None` if the end can't be reached from `start`.
+    fn path_from(&self, field: &DistanceField, start: &Location) -> Option<Path> {
+        let mut locations = vec![start.clone()];
+        let mut dist = field.distance(start)?;
+        while dist > 0 {
+            let current = locations.last()?;
+            let (next, next_dist) = self.neighbors(current).find_map(|(_, neighbor)| {
+                let cost = self.step_cost(current, &neighbor)?;
+                let next_dist = field.distance(&neighbor)?;
+                (next_dist + cost == dist).then_some((neighbor, next_dist))
+            })?;
+            locations.push(next);
+            dist = next_dist;
+        }
+        Some(Path { locations })
+    }
+
+    /// The shortest path from any of the lowest locations (including the
+    /// start, which is also at the lowest height).
+    fn best_lowest_path(&self, field: &DistanceField) -> Option<Path> {
+        let start = self
+            .heights
+            .iter()
+            .enumerate()
+            .flat_map(|(row, heights)| {
+                heights
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &height)| height == 0)
+                    .map(move |(col, _)| Location { row, col })
+            })
+            .filter_map(|location| Some((field.distance(&location)?, location)))
+            .min()?
+            .1;
+        self.path_from(field, &start)
+    }
+
+    /// Draw the path the way the puzzle does, e.g.,
+    ///
+    /// ```text
+    /// v..v<<<<
+    /// >v.vv<<^
+    /// .>vv>E^^
+    /// ..v>>>^^
+    /// ..>>>>>^
+    /// ```
+    fn render_path(&self, path: &Path) -> String {
+        let mut map = self
+            .heights
+            .iter()
+            .map(|row| vec!['.'; row.len()])
+            .collect::<Vec<_>>();
+        for step in path.locations.windows(2) {
+            if let Some((direction, _)) = self
+                .neighbors(&step[0])
+                .find(|(_, neighbor)| *neighbor == step[1])
+            {
+                map[step[0].row][step[0].col] = direction.arrow();
+            }
+        }
+        map[self.end.row][self.end.col] = 'E';
+        map.iter()
+            .map(|row| row.iter().collect::<String>() + "\n")
+            .collect()
     }
 }
 
 static INPUT_FILE: &str = "../inputs/day_12.input";
 
+static USAGE: &str = "Usage: [--map] [--max-ascent <n>] [--max-descent <n>] [--height-cost <n>] [--elevations <chars>]";
+
+struct Options {
+    show_maps: bool,
+    rule: ClimbRule,
+    elevations: Elevations,
+}
+
+fn parse_number<T: FromStr>(arg: Option<String>) -> Result<T> {
+    let arg = arg.context(USAGE)?;
+    arg.parse()
+        .map_err(|_| anyhow::anyhow!("Couldn't parse '{arg}' as a number\n{USAGE}"))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut options = Options {
+        show_maps: false,
+        rule: ClimbRule::default(),
+        elevations: Elevations::new(STANDARD_ELEVATIONS)?,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--map" => options.show_maps = true,
+            "--max-ascent" => options.rule.max_ascent = parse_number(args.next())?,
+            "--max-descent" => options.rule.max_descent = Some(parse_number(args.next())?),
+            "--height-cost" => options.rule.height_cost = parse_number(args.next())?,
+            "--elevations" => {
+                options.elevations = Elevations::new(&args.next().context(USAGE)?)?;
+            }
+            _ => bail!("Unknown option '{arg}'\n{USAGE}"),
+        }
+    }
+    Ok(options)
+}
+
 fn main() -> Result<()> {
-    let terrain = fs::read_to_string(INPUT_FILE)
-        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?
-        .parse::<Terrain>()?;
+    let options = parse_args(env::args().skip(1))?;
+
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+    let terrain = Terrain::parse(&contents, &options.elevations)?.with_rule(options.rule);
 
-    let result = (0..terrain.heights.len())
-        .filter_map(|row| terrain.shortest_path_length(Location { row, col: 0 }).ok())
-        .min()
+    let field = terrain.distances_to_end();
+
+    let from_start = terrain
+        .path_from(&field, &terrain.start)
+        .context("There's no path from the start to the end!")?;
+    let from_lowest = terrain
+        .best_lowest_path(&field)
         .context("There were no solutions found!")?;
 
-    println!("The shortest path length was {result}.");
+    println!("The shortest path from the start was {}.", from_start.len());
+    let lowest_start = from_lowest.start();
+    println!(
+        "The shortest path length was {} (starting at row {}, column {}).",
+        from_lowest.len(),
+        lowest_start.row,
+        lowest_start.col
+    );
+    if terrain.rule.height_cost > 0 {
+        // We found both paths in the field, so they both have distances.
+        let cost = |location| field.distance(location).unwrap_or_default();
+        println!(
+            "With a cost of {} for each unit of height change, those routes cost {} and {}.",
+            terrain.rule.height_cost,
+            cost(from_start.start()),
+            cost(lowest_start)
+        );
+    }
+
+    if options.show_maps {
+        println!("\nFrom the start:\n{}", terrain.render_path(&from_start));
+        println!(
+            "From the best lowest location:\n{}",
+            terrain.render_path(&from_lowest)
+        );
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod path_tests {
+    use super::*;
+
+    static TEST_INPUT_FILE: &str = "../inputs/day_12_test.input";
+
+    #[test]
+    fn example() -> Result<()> {
+        let terrain = fs::read_to_string(TEST_INPUT_FILE)?.parse::<Terrain>()?;
+        let field = terrain.distances_to_end();
+        assert_eq!(Some(31), field.distance(&terrain.start));
+        let from_start = terrain
+            .path_from(&field, &terrain.start)
+            .context("No path from the start")?;
+        assert_eq!(31, from_start.len());
+        // This is a different shortest path than the one in the puzzle,
+        // which goes right at the third row instead of down.
+        assert_eq!(
+            "v..v<<<<\n>v.vv<<^\n.v.v>E^^\n.>v>>>^^\n..>>>>>^\n",
+            terrain.render_path(&from_start)
+        );
+        let from_lowest = terrain
+            .best_lowest_path(&field)
+            .context("No path from any a")?;
+        assert_eq!(29, from_lowest.len());
+        Ok(())
+    }
+
+    #[test]
+    fn input() -> Result<()> {
+        let terrain = fs::read_to_string(INPUT_FILE)?.parse::<Terrain>()?;
+        let field = terrain.distances_to_end();
+        assert_eq!(Some(380), field.distance(&terrain.start));
+        let from_lowest = terrain
+            .best_lowest_path(&field)
+            .context("No path from any a")?;
+        assert_eq!(375, from_lowest.len());
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable() -> Result<()> {
+        let terrain = "SazE".parse::<Terrain>()?;
+        let field = terrain.distances_to_end();
+        assert_eq!(None, field.distance(&terrain.start));
+        assert_eq!(Some(1), field.distance(&Location { row: 0, col: 2 }));
+        assert!(terrain.path_from(&field, &terrain.start).is_none());
+        assert!(terrain.best_lowest_path(&field).is_none());
+        assert!("SaSE".parse::<Terrain>().is_err());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod climb_rule_tests {
+    use super::*;
+
+    #[test]
+    fn unknown_elevations() {
+        let error = "SabC\nabcE"
+            .parse::<Terrain>()
+            .map_or_else(|e| e.to_string(), |_| String::new());
+        assert_eq!("Unknown elevation 'C' on line 1, column 4", error);
+        assert!(Elevations::new("").is_err());
+        assert!(Elevations::new("abca").is_err());
+        assert!(Elevations::new("aSb").is_err());
+    }
+
+    #[test]
+    fn other_elevations() -> Result<()> {
+        let elevations = Elevations::new("0123456789")?;
+        let terrain = Terrain::parse("S1234\n98765\nE9999", &elevations)?;
+        let field = terrain.distances_to_end();
+        // We can't go straight down from `S` (the `9` is too high), so we
+        // wind through every digit to get to the `9`s next to `E`.
+        assert_eq!(Some(10), field.distance(&terrain.start));
+        Ok(())
+    }
+
+    #[test]
+    fn climb_rules() -> Result<()> {
+        let terrain = "SbcdE\nabcyz".parse::<Terrain>()?;
+        assert_eq!(None, terrain.distances_to_end().distance(&terrain.start));
+
+        let big_steps = ClimbRule {
+            max_ascent: 25,
+            ..ClimbRule::default()
+        };
+        let terrain = terrain.with_rule(big_steps);
+        assert_eq!(Some(4), terrain.distances_to_end().distance(&terrain.start));
+
+        // Every route climbs at least 25, so the best one costs its 4
+        // steps plus 25.
+        let weighted = ClimbRule {
+            height_cost: 1,
+            ..big_steps
+        };
+        let terrain = terrain.with_rule(weighted);
+        assert_eq!(
+            Some(4 + 25),
+            terrain.distances_to_end().distance(&terrain.start)
+        );
+
+        // We have to come down from the `b` to get to the end.
+        let terrain = "SbaE".parse::<Terrain>()?.with_rule(big_steps);
+        assert_eq!(Some(3), terrain.distances_to_end().distance(&terrain.start));
+        let terrain = terrain.with_rule(ClimbRule {
+            max_descent: Some(0),
+            ..big_steps
+        });
+        assert_eq!(None, terrain.distances_to_end().distance(&terrain.start));
+        Ok(())
+    }
+
+    #[test]
+    fn expensive_dead_end() -> Result<()> {
+        // The cheap route is `S`, `a`, `E`, which climbs once. Going
+        // down to the `b` in the bottom left and back up costs two more
+        // climbs, which is more than a `u32` can hold.
+        let elevations = Elevations::new("ab")?;
+        let terrain = Terrain::parse("SaE\nbab", &elevations)?.with_rule(ClimbRule {
+            height_cost: 1 << 31,
+            ..ClimbRule::default()
+        });
+        let field = terrain.distances_to_end();
+        assert_eq!(Some(2 + (1 << 31)), field.distance(&terrain.start));
+        assert_eq!(
+            Some(3 + (1 << 32)),
+            field.distance(&Location { row: 1, col: 0 })
+        );
+        let path = terrain
+            .path_from(&field, &terrain.start)
+            .context("No path from the start")?;
+        assert_eq!(2, path.len());
+        Ok(())
+    }
+}
@@ -9,7 +9,14 @@ use nom::{
     bytes::complete::tag, character::complete::char, combinator::map, multi::separated_list0,
     sequence::separated_pair, IResult,
 };
-use std::{collections::HashSet, fmt::Display, fs, ops::RangeInclusive};
+use rust_advent::history::{Mode, Snapshot};
+use std::{
+    collections::{BTreeSet, HashSet},
+    env,
+    fmt::Display,
+    fs,
+    ops::RangeInclusive,
+};
 
 #[derive(Debug, Hash, PartialEq, Eq)]
 struct Point {
@@ -132,6 +139,26 @@ impl Display for Cave {
     }
 }
 
+// We record each occupied point as `x,y`, which is the same format as
+// the points in the input.
+impl Snapshot for Cave {
+    fn entries(&self) -> BTreeSet<String> {
+        self.occupied
+            .iter()
+            .map(|Point { x, y }| format!("{x},{y}"))
+            .collect()
+    }
+
+    fn restore(&mut self, entries: &BTreeSet<String>) -> Result<()> {
+        *self = Self::default();
+        for entry in entries {
+            let (_, p) = point(entry).map_err(nom::Err::<nom::error::Error<&str>>::to_owned)?;
+            self.insert(p);
+        }
+        Ok(())
+    }
+}
+
 fn point(s: &str) -> IResult<&str, Point> {
     map(
         separated_pair(
@@ -155,6 +182,8 @@ fn parse_path(s: &str) -> anyhow::Result<Path> {
 static INPUT_FILE: &str = "../inputs/day_14.input";
 
 fn main() -> anyhow::Result<()> {
+    let mode = Mode::from_args(env::args().skip(1))?;
+
     let paths: Vec<Path> = fs::read_to_string(INPUT_FILE)
         .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?
         .lines()
@@ -171,9 +200,14 @@ fn main() -> anyhow::Result<()> {
 
     // println!("Our starting cave is\n{cave}");
 
+    let Some(mut recorder) = mode.replay(&mut cave)? else {
+        return Ok(());
+    };
+    recorder.record(&cave)?;
     while cave.add_sand() {
-        // Do nothing
+        recorder.record(&cave)?;
     }
+    recorder.finish()?;
     println!("Our ending cave is\n{cave}");
     println!("We added {} units of sand to the cave.", cave.num_sands);
 
@@ -15,6 +15,7 @@ use nom::{
     sequence::preceded,
     IResult,
 };
+use rust_advent::profile::phase;
 
 #[derive(Debug)]
 struct Valve {
@@ -203,20 +204,26 @@ impl Cave {
 static INPUT_FILE: &str = "../inputs/day_16.input";
 
 fn main() -> anyhow::Result<()> {
-    let valves = fs::read_to_string(INPUT_FILE)
-        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?
-        .lines()
-        .map(extract_valve)
-        .collect::<anyhow::Result<Vec<Valve>>>()?;
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+
+    let valves = phase("parse", || {
+        contents
+            .lines()
+            .map(extract_valve)
+            .collect::<anyhow::Result<Vec<Valve>>>()
+    })?;
 
     println!("{valves:?}");
 
     let cave = Cave::new(valves);
 
-    let result = cave.max_release(
-        State::new("AA".to_string(), BitSet::default(), 30),
-        &mut HashMap::new(),
-    )?;
+    let result = phase("part 1", || {
+        cave.max_release(
+            State::new("AA".to_string(), BitSet::default(), 30),
+            &mut HashMap::new(),
+        )
+    })?;
 
     println!("The maximum release is {result}");
 
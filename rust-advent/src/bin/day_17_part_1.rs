@@ -4,7 +4,19 @@
 #![warn(clippy::expect_used)]
 
 use anyhow::{bail, Context};
-use std::{collections::HashSet, fmt::Display, fs, iter::Cycle, ops::Not, vec::IntoIter};
+use rust_advent::{
+    history::{Mode, Recorder, Snapshot},
+    profile::phase,
+};
+use std::{
+    collections::{BTreeSet, HashSet},
+    env,
+    fmt::Display,
+    fs,
+    iter::Cycle,
+    ops::Not,
+    vec::IntoIter,
+};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
@@ -215,12 +227,14 @@ impl Chamber {
         }
     }
 
-    fn drop_rocks(&mut self, num_rocks: u32) {
+    fn drop_rocks(&mut self, num_rocks: u32, recorder: &mut Recorder) -> anyhow::Result<()> {
         for _ in 0..num_rocks {
             let mut positioned_rock = self.next_rock();
             self.drop_rock(&mut positioned_rock);
+            recorder.record(self)?;
             // println!("{self}\n\n");
         }
+        Ok(())
     }
 
     fn drop_rock(&mut self, rock: &mut PositionedRock) {
@@ -265,19 +279,55 @@ impl Chamber {
     }
 }
 
+// We only record the occupied positions (as `x,y`); which rock and jet
+// come next isn't needed to draw the chamber.
+impl Snapshot for Chamber {
+    fn entries(&self) -> BTreeSet<String> {
+        self.occupied
+            .iter()
+            .map(|Position { x, y }| format!("{x},{y}"))
+            .collect()
+    }
+
+    fn restore(&mut self, entries: &BTreeSet<String>) -> anyhow::Result<()> {
+        self.occupied = entries
+            .iter()
+            .map(|entry| {
+                let (x, y) = entry
+                    .split_once(',')
+                    .with_context(|| format!("Missing ',' in position '{entry}'"))?;
+                Ok(Position::new(x.parse()?, y.parse()?))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        self.highest_rock_point = self.occupied.iter().map(|p| p.y).max().unwrap_or(0);
+        Ok(())
+    }
+}
+
 static INPUT_FILE: &str = "../inputs/day_17.input";
 
 fn main() -> anyhow::Result<()> {
-    let jet_directions = fs::read_to_string(INPUT_FILE)
-        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?
-        .trim()
-        .chars()
-        .map(TryInto::<JetDirection>::try_into)
-        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mode = Mode::from_args(env::args().skip(1))?;
+
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+
+    let jet_directions = phase("parse", || {
+        contents
+            .trim()
+            .chars()
+            .map(TryInto::<JetDirection>::try_into)
+            .collect::<anyhow::Result<Vec<_>>>()
+    })?;
 
     let mut chamber = Chamber::new(jet_directions);
 
-    chamber.drop_rocks(2022);
+    let Some(mut recorder) = mode.replay(&mut chamber)? else {
+        return Ok(());
+    };
+    recorder.record(&chamber)?;
+    phase("part 1", || chamber.drop_rocks(2022, &mut recorder))?;
+    recorder.finish()?;
 
     println!("The tower height is {}", chamber.highest_rock_point);
 
@@ -4,6 +4,7 @@
 #![warn(clippy::expect_used)]
 
 use anyhow::{bail, Context};
+use rust_advent::profile::phase;
 use std::{
     collections::HashMap,
     fmt::Display,
@@ -346,12 +347,16 @@ impl Chamber {
 static INPUT_FILE: &str = "../inputs/day_17.input";
 
 fn main() -> anyhow::Result<()> {
-    let jet_directions = fs::read_to_string(INPUT_FILE)
-        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?
-        .trim()
-        .chars()
-        .map(TryInto::<JetDirection>::try_into)
-        .collect::<anyhow::Result<Vec<_>>>()?;
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+
+    let jet_directions = phase("parse", || {
+        contents
+            .trim()
+            .chars()
+            .map(TryInto::<JetDirection>::try_into)
+            .collect::<anyhow::Result<Vec<_>>>()
+    })?;
 
     let num_jet_directions = jet_directions.len();
     let num_jet_directions: u64 = u64::try_from(num_jet_directions)?;
@@ -382,7 +387,7 @@ fn main() -> anyhow::Result<()> {
     // let num_rocks: u64 = 1_000_000_000_000;
 
     // chamber.drop_rocks(10_000);
-    chamber.drop_rocks(1742 + 1583);
+    phase("part 2", || chamber.drop_rocks(1742 + 1583));
     println!(
         "Height after {} rocks is {}",
         1742 + 1583,
@@ -405,7 +410,7 @@ fn main() -> anyhow::Result<()> {
     // 1742 1583
     // The height will be 5149 + 2694 * 579,710,143 = 1,561,739,130,391
 
-    if let Some((start, length)) = chamber.find_cycle() {
+    if let Some((start, length)) = phase("find cycle", || chamber.find_cycle()) {
         println!("We found a cycle that starts at {start} and has length {length}.");
     } else {
         println!("We failed to find a cycle!");
@@ -6,6 +6,7 @@
 use anyhow::Context;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use rust_advent::profile::phase;
 use std::{
     fs,
     marker::PhantomData,
@@ -364,17 +365,23 @@ impl Blueprint {
 static INPUT_FILE: &str = "../inputs/day_19.input";
 
 fn main() -> anyhow::Result<()> {
-    let blueprints: Vec<Blueprint> = fs::read_to_string(INPUT_FILE)
-        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?
-        .trim()
-        .lines()
-        .map(str::parse)
-        .take(3)
-        .collect::<Result<Vec<Blueprint>, _>>()?;
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+
+    let blueprints: Vec<Blueprint> = phase("parse", || {
+        contents
+            .trim()
+            .lines()
+            .map(str::parse)
+            .take(3)
+            .collect::<Result<Vec<Blueprint>, _>>()
+    })?;
 
     println!("The blueprints are {blueprints:?}");
 
-    let result: usize = blueprints.iter().map(Blueprint::quality_level).product();
+    let result: usize = phase("part 2", || {
+        blueprints.iter().map(Blueprint::quality_level).product()
+    });
 
     println!("The result is {result}.");
 
@@ -5,11 +5,12 @@
 
 use anyhow::Context;
 use itertools::Itertools;
+use rust_advent::history::{Mode, Recorder, Snapshot};
 use std::fmt::Display;
 use std::ops::{Add, Not};
 use std::{
-    collections::{HashMap, HashSet},
-    fs,
+    collections::{BTreeSet, HashMap, HashSet},
+    env, fs,
 };
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -148,6 +149,29 @@ impl Display for Board {
     }
 }
 
+// Each elf is recorded as its `row,col` position.
+impl Snapshot for Board {
+    fn entries(&self) -> BTreeSet<String> {
+        self.elves
+            .iter()
+            .map(|Elf { position }| format!("{},{}", position.row, position.col))
+            .collect()
+    }
+
+    fn restore(&mut self, entries: &BTreeSet<String>) -> anyhow::Result<()> {
+        self.elves = entries
+            .iter()
+            .map(|entry| {
+                let (row, col) = entry
+                    .split_once(',')
+                    .with_context(|| format!("Missing ',' in position '{entry}'"))?;
+                Ok(Elf::new(row.parse()?, col.parse()?))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(())
+    }
+}
+
 fn parse_map_row(row: isize, s: &str) -> Vec<Elf> {
     #[allow(clippy::unwrap_used)]
     s.chars()
@@ -223,15 +247,20 @@ fn one_round(board: &Board, directions: &Vec<Direction>) -> Board {
     }
 }
 
-fn disperse_elves(mut board: Board, num_rounds: usize) -> Board {
+fn disperse_elves(
+    mut board: Board,
+    num_rounds: usize,
+    recorder: &mut Recorder,
+) -> anyhow::Result<Board> {
     let mut directions_cycle = Direction::cycle();
 
     for _ in 0..num_rounds {
         let directions = directions_cycle.by_ref().take(4).collect();
         directions_cycle.next();
         board = one_round(&board, &directions);
+        recorder.record(&board)?;
     }
-    board
+    Ok(board)
 }
 
 static INPUT_FILE: &str = "../inputs/day_23.input";
@@ -242,7 +271,13 @@ fn main() -> anyhow::Result<()> {
     let file = fs::read_to_string(INPUT_FILE)
         .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
 
-    let board = parse_map(&file);
+    let mode = Mode::from_args(env::args().skip(1))?;
+
+    let mut board = parse_map(&file);
+    let Some(mut recorder) = mode.replay(&mut board)? else {
+        return Ok(());
+    };
+    recorder.record(&board)?;
     println!("Initial board: \n{board}");
 
     // let board = one_round(&board, &Direction::cycle().take(4).collect());
@@ -251,7 +286,8 @@ fn main() -> anyhow::Result<()> {
     // let board = one_round(&board, &Direction::cycle().skip(1).take(4).collect());
     // println!("After two_rounds: \n{board}");
 
-    let final_elves = disperse_elves(board, NUM_ROUNDS);
+    let final_elves = disperse_elves(board, NUM_ROUNDS, &mut recorder)?;
+    recorder.finish()?;
 
     println!("After dispersal: \n{final_elves}");
 
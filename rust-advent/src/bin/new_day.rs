@@ -0,0 +1,270 @@
+#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+#![warn(clippy::unwrap_used)]
+#![warn(clippy::expect_used)]
+
+use anyhow::{bail, ensure, Context, Result};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+// All of these paths are relative to the `rust-advent` directory, which
+// is where `cargo run` is called from, just like the `INPUT_FILE`
+// paths in each of the days.
+static BIN_DIR: &str = "src/bin";
+static BENCH_DIR: &str = "benches";
+static INPUT_DIR: &str = "../inputs";
+static CARGO_TOML: &str = "Cargo.toml";
+
+// The templates use `__DAY__` for the two digit day number (e.g., `07`)
+// and `__NAME__` for the name of the binary (e.g., `day_07_part_1`).
+static SOLVER_TEMPLATE: &str = r#"#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+#![warn(clippy::unwrap_used)]
+#![warn(clippy::expect_used)]
+
+use anyhow::{Context, Result};
+use std::fs;
+
+static INPUT_FILE: &str = "../inputs/day___DAY__.input";
+
+fn main() -> Result<()> {
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))?;
+
+    let result = solve(&contents);
+
+    println!("The result was {result}");
+
+    Ok(())
+}
+
+// TODO: Replace this with the actual solution.
+fn solve(contents: &str) -> usize {
+    contents.lines().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT_FILE: &str = "../inputs/day___DAY___test.input";
+
+    #[test]
+    fn check_test_input() -> Result<()> {
+        let contents = fs::read_to_string(TEST_INPUT_FILE)
+            .with_context(|| format!("Failed to open file '{TEST_INPUT_FILE}'"))?;
+        // TODO: Replace this with the expected answer from the puzzle description.
+        assert_eq!(0, solve(&contents));
+        Ok(())
+    }
+}
+"#;
+
+static BENCH_TEMPLATE: &str = r#"use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+
+use anyhow::Context;
+
+// Benchmarks can't see the code in `src/bin`, so copy the functions
+// being benchmarked into this file.
+// TODO: Replace this with the solution code from `src/bin/__NAME__.rs`.
+fn solve(contents: &str) -> usize {
+    contents.lines().count()
+}
+
+static INPUT_FILE: &str = "../inputs/day___DAY__.input";
+
+fn solve_benchmark(c: &mut Criterion) {
+    let contents = fs::read_to_string(INPUT_FILE)
+        .with_context(|| format!("Failed to open file '{INPUT_FILE}'"))
+        .unwrap();
+
+    c.bench_function("solve", |b| b.iter(|| solve(&contents)));
+}
+
+criterion_group!(__NAME___bench, solve_benchmark);
+criterion_main!(__NAME___bench);
+"#;
+
+static BENCH_ENTRY_TEMPLATE: &str = r#"
+[[bench]]
+name = "__NAME___bench"
+harness = false
+"#;
+
+struct NewDay {
+    day: u8,
+    part: Option<u8>,
+}
+
+impl NewDay {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let day = args
+            .next()
+            .context("Usage: new_day <day> [<part>]")?
+            .parse::<u8>()
+            .context("The day must be a number")?;
+        ensure!(
+            (1..=25).contains(&day),
+            "The day must be in 1..=25, not {day}"
+        );
+        let part = args
+            .next()
+            .map(|part| part.parse::<u8>().context("The part must be a number"))
+            .transpose()?;
+        if let Some(part) = part {
+            ensure!(
+                (1..=2).contains(&part),
+                "The part must be 1 or 2, not {part}"
+            );
+        }
+        Ok(Self { day, part })
+    }
+
+    fn day_str(&self) -> String {
+        format!("{:02}", self.day)
+    }
+
+    // Days that were solved with one program (like day 1) are just
+    // `day_01`, while the others are `day_07_part_1`, etc.
+    fn name(&self) -> String {
+        let day = format!("day_{}", self.day_str());
+        self.part
+            .map_or_else(|| day.clone(), |part| format!("{day}_part_{part}"))
+    }
+
+    fn fill_in(&self, template: &str) -> String {
+        template
+            .replace("__DAY__", &self.day_str())
+            .replace("__NAME__", &self.name())
+    }
+
+    fn solver_path(&self) -> PathBuf {
+        Path::new(BIN_DIR).join(format!("{}.rs", self.name()))
+    }
+
+    fn bench_path(&self) -> PathBuf {
+        Path::new(BENCH_DIR).join(format!("{}_bench.rs", self.name()))
+    }
+
+    fn test_input_path(&self) -> PathBuf {
+        Path::new(INPUT_DIR).join(format!("day_{}_test.input", self.day_str()))
+    }
+
+    // Part 2 of a day will often share the test input with part 1, so
+    // it's fine if the test input is already there; we just won't
+    // create (or touch) it.
+    fn files(&self) -> Vec<(PathBuf, String)> {
+        let mut files = vec![
+            (self.solver_path(), self.fill_in(SOLVER_TEMPLATE)),
+            (self.bench_path(), self.fill_in(BENCH_TEMPLATE)),
+        ];
+        if !self.test_input_path().exists() {
+            files.push((self.test_input_path(), String::new()));
+        }
+        files
+    }
+}
+
+// `create_new` guarantees that we never overwrite an existing file, even
+// if one snuck in after we checked.
+fn create_file(path: &Path, contents: &str) -> Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("Failed to create file '{}'", path.display()))?
+        .write_all(contents.as_bytes())
+        .with_context(|| format!("Failed to write to file '{}'", path.display()))
+}
+
+// The benchmark is the only thing that needs registering. Each day is
+// its own binary, which Cargo finds in `src/bin` on its own, so there's
+// no table of days for the solver to be added to.
+fn register_bench(new_day: &NewDay) -> Result<()> {
+    let cargo_toml = fs::read_to_string(CARGO_TOML)
+        .with_context(|| format!("Failed to open file '{CARGO_TOML}'"))?;
+    let bench_name = format!("name = \"{}_bench\"", new_day.name());
+    if cargo_toml.contains(&bench_name) {
+        return Ok(());
+    }
+    OpenOptions::new()
+        .append(true)
+        .open(CARGO_TOML)
+        .with_context(|| format!("Failed to open file '{CARGO_TOML}' for appending"))?
+        .write_all(new_day.fill_in(BENCH_ENTRY_TEMPLATE).as_bytes())
+        .with_context(|| format!("Failed to append to file '{CARGO_TOML}'"))
+}
+
+fn main() -> Result<()> {
+    let new_day = NewDay::from_args(env::args().skip(1))?;
+    let files = new_day.files();
+
+    // Check everything before we create anything so we don't end up
+    // with half a day if one of the files already exists.
+    let existing = files
+        .iter()
+        .map(|(path, _)| path)
+        .filter(|path| path.exists())
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    if !existing.is_empty() {
+        bail!("Refusing to overwrite existing files: {existing:?}");
+    }
+
+    for (path, contents) in &files {
+        create_file(path, contents)?;
+        println!("Created {}", path.display());
+    }
+    register_bench(&new_day)?;
+    println!("Registered {}_bench in {CARGO_TOML}", new_day.name());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn names() -> Result<()> {
+        assert_eq!("day_07", NewDay::from_args(args(&["7"]))?.name());
+        assert_eq!(
+            "day_07_part_2",
+            NewDay::from_args(args(&["7", "2"]))?.name()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn bad_args() {
+        assert!(NewDay::from_args(args(&[])).is_err());
+        assert!(NewDay::from_args(args(&["26"])).is_err());
+        assert!(NewDay::from_args(args(&["7", "3"])).is_err());
+    }
+
+    #[test]
+    fn fill_in_templates() -> Result<()> {
+        let new_day = NewDay::from_args(args(&["7", "1"]))?;
+        let solver = new_day.fill_in(SOLVER_TEMPLATE);
+        assert!(solver.contains("\"../inputs/day_07.input\""));
+        assert!(solver.contains("\"../inputs/day_07_test.input\""));
+        let bench = new_day.fill_in(BENCH_TEMPLATE);
+        assert!(bench.contains("criterion_group!(day_07_part_1_bench, solve_benchmark);"));
+        assert!(new_day
+            .fill_in(BENCH_ENTRY_TEMPLATE)
+            .contains("name = \"day_07_part_1_bench\""));
+        Ok(())
+    }
+}
@@ -0,0 +1,476 @@
+// The CPU and CRT from day 10, shared by the solver (`day_10_part_2`) and
+// the interactive debugger (`day_10_debugger`) so the two can't drift
+// apart.
+//
+// The CPU is a small register machine driven by a table of instructions.
+// The puzzle's `noop` and `addx` are in the table, along with a few extra
+// instructions (`add`, `set`, `jmp`, `jnz`) for writing more interesting
+// programs. Things that want to watch the CPU run (like the CRT) are
+// `Observer`s, which see the registers during every cycle.
+
+use anyhow::{ensure, Context, Result};
+use std::{fmt::Display, rc::Rc, str::FromStr};
+
+/// The names of the VM's registers. The puzzle only uses `x`, which
+/// starts at 1; the others start at 0.
+pub const REGISTER_NAMES: [&str; 4] = ["x", "y", "z", "w"];
+pub const X: Register = Register(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register(usize);
+
+impl FromStr for Register {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        REGISTER_NAMES
+            .iter()
+            .position(|&name| name == s)
+            .map(Self)
+            .with_context(|| format!("Unknown register '{s}'; expected one of {REGISTER_NAMES:?}"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registers([isize; REGISTER_NAMES.len()]);
+
+impl Default for Registers {
+    fn default() -> Self {
+        let mut registers = [0; REGISTER_NAMES.len()];
+        registers[X.0] = 1;
+        Self(registers)
+    }
+}
+
+impl Registers {
+    #[must_use]
+    pub const fn get(&self, register: Register) -> isize {
+        self.0[register.0]
+    }
+
+    const fn value(&self, operand: Operand) -> isize {
+        match operand {
+            Operand::Register(register) => self.get(register),
+            Operand::Immediate(value) => value,
+        }
+    }
+}
+
+impl Display for Registers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (name, value)) in REGISTER_NAMES.iter().zip(self.0).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What an instruction accepts in each operand position. A `Source` can
+/// be either a register or an immediate value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperandKind {
+    Register,
+    Immediate,
+    Source,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(Register),
+    Immediate(isize),
+}
+
+impl OperandKind {
+    fn parse(self, s: &str) -> Result<Operand> {
+        let immediate = || {
+            s.parse()
+                .map(Operand::Immediate)
+                .with_context(|| format!("Couldn't parse '{s}' as a value"))
+        };
+        match self {
+            Self::Register => s.parse().map(Operand::Register),
+            Self::Immediate => immediate(),
+            Self::Source => s.parse().map(Operand::Register).or_else(|_| immediate()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opcode {
+    Noop,
+    AddX,
+    Add,
+    Set,
+    Jump,
+    JumpIfNotZero,
+}
+
+/// An entry in the instruction table: the name an instruction has in
+/// the program, the operands it takes, and how many cycles it takes to
+/// run. An instruction's effect happens at the end of its last cycle.
+#[derive(Debug)]
+struct OpcodeInfo {
+    opcode: Opcode,
+    name: &'static str,
+    operands: &'static [OperandKind],
+    cycles: usize,
+}
+
+// `jmp` and `jnz` offsets are relative to the jump instruction, so
+// `jmp 1` is the same as a `noop`, and `jnz x -2` jumps back two
+// instructions if `x` isn't zero.
+static INSTRUCTION_SET: [OpcodeInfo; 6] = [
+    OpcodeInfo {
+        opcode: Opcode::Noop,
+        name: "noop",
+        operands: &[],
+        cycles: 1,
+    },
+    OpcodeInfo {
+        opcode: Opcode::AddX,
+        name: "addx",
+        operands: &[OperandKind::Immediate],
+        cycles: 2,
+    },
+    OpcodeInfo {
+        opcode: Opcode::Add,
+        name: "add",
+        operands: &[OperandKind::Register, OperandKind::Source],
+        cycles: 2,
+    },
+    OpcodeInfo {
+        opcode: Opcode::Set,
+        name: "set",
+        operands: &[OperandKind::Register, OperandKind::Source],
+        cycles: 1,
+    },
+    OpcodeInfo {
+        opcode: Opcode::Jump,
+        name: "jmp",
+        operands: &[OperandKind::Source],
+        cycles: 1,
+    },
+    OpcodeInfo {
+        opcode: Opcode::JumpIfNotZero,
+        name: "jnz",
+        operands: &[OperandKind::Source, OperandKind::Source],
+        cycles: 1,
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    info: &'static OpcodeInfo,
+    operands: Vec<Operand>,
+}
+
+impl FromStr for Instruction {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let mut fields = line.split_ascii_whitespace();
+        let name = fields
+            .next()
+            .with_context(|| format!("No instruction in line '{line}'"))?;
+        let info = INSTRUCTION_SET
+            .iter()
+            .find(|info| info.name == name)
+            .with_context(|| format!("Unknown instruction '{name}' in line '{line}'"))?;
+        let fields = fields.collect::<Vec<_>>();
+        ensure!(
+            fields.len() == info.operands.len(),
+            "The instruction '{name}' takes {} operand(s), but line '{line}' has {}",
+            info.operands.len(),
+            fields.len()
+        );
+        let operands = info
+            .operands
+            .iter()
+            .zip(fields)
+            .map(|(kind, field)| kind.parse(field))
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Failed to parse the operands in line '{line}'"))?;
+        Ok(Self { info, operands })
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.info.name)?;
+        for operand in &self.operands {
+            match operand {
+                Operand::Register(register) => write!(f, " {}", REGISTER_NAMES[register.0])?,
+                Operand::Immediate(value) => write!(f, " {value}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Something that wants to watch the VM run. It's called once _during_
+/// each cycle, before the effects of any instruction that finishes on
+/// that cycle.
+pub trait Observer {
+    fn observe(&mut self, cycle: usize, registers: &Registers);
+}
+
+pub const NUM_SCREEN_ROWS: usize = 6;
+pub const NUM_SCREEN_COLS: usize = 40;
+pub const NUM_PIXELS: usize = NUM_SCREEN_ROWS * NUM_SCREEN_COLS;
+
+#[derive(Debug, Clone)]
+pub struct Screen {
+    pub rows: [[char; NUM_SCREEN_COLS]; NUM_SCREEN_ROWS],
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self {
+            rows: [[' '; NUM_SCREEN_COLS]; NUM_SCREEN_ROWS],
+        }
+    }
+}
+
+impl Display for Screen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.rows {
+            for c in row {
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The CRT draws one pixel each cycle, lighting it if the three-pixel-wide
+/// sprite centered on `x` covers it.
+impl Observer for Screen {
+    // This is a cheap hack, but I really don't want to "properly" deal with the cast at the moment.
+    #[allow(clippy::cast_possible_wrap)]
+    fn observe(&mut self, cycle: usize, registers: &Registers) {
+        if !(1..=NUM_PIXELS).contains(&cycle) {
+            return;
+        }
+        let (row, col) = ((cycle - 1) / NUM_SCREEN_COLS, (cycle - 1) % NUM_SCREEN_COLS);
+        let x = registers.get(X);
+        self.rows[row][col] = if (x - 1..=x + 1).contains(&(col as isize)) {
+            '#'
+        } else {
+            '.'
+        };
+    }
+}
+
+/// The CPU: it runs a program a cycle at a time, letting each of the
+/// observers see every cycle. The program is shared (not copied) between
+/// clones, so cloning a `Vm` to remember its state is cheap.
+#[derive(Debug, Clone)]
+pub struct Vm {
+    program: Rc<[Instruction]>,
+    registers: Registers,
+    program_counter: usize,
+    // The number of the cycle that's about to start.
+    cycle: usize,
+    // How many cycles of the current instruction have already run.
+    elapsed: usize,
+}
+
+impl Vm {
+    #[must_use]
+    pub fn new(program: impl Into<Rc<[Instruction]>>) -> Self {
+        Self {
+            program: program.into(),
+            registers: Registers::default(),
+            program_counter: 0,
+            cycle: 1,
+            elapsed: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    #[must_use]
+    pub const fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+
+    /// The number of the cycle that's about to start; the registers are
+    /// the values they'll have _during_ that cycle.
+    #[must_use]
+    pub const fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    #[must_use]
+    pub fn program_len(&self) -> usize {
+        self.program.len()
+    }
+
+    /// The instruction that's running, or `None` once the program has
+    /// halted.
+    #[must_use]
+    pub fn current_instruction(&self) -> Option<&Instruction> {
+        self.program.get(self.program_counter)
+    }
+
+    /// How many more cycles (including the one that's about to start)
+    /// the current instruction needs before it takes effect.
+    #[must_use]
+    pub fn remaining_cycles(&self) -> usize {
+        self.current_instruction()
+            .map_or(0, |instruction| instruction.info.cycles - self.elapsed)
+    }
+
+    /// The program halts when the program counter leaves the program,
+    /// either by running off the end or by jumping outside of it.
+    #[must_use]
+    pub fn is_halted(&self) -> bool {
+        self.program_counter >= self.program.len()
+    }
+
+    /// Run one cycle. Once the program has halted, the VM idles (as if
+    /// running `noop`s) with the final registers, so the observers still
+    /// see every cycle and the CRT gets fully drawn.
+    pub fn tick(&mut self, observers: &mut [&mut dyn Observer]) {
+        for observer in observers.iter_mut() {
+            observer.observe(self.cycle, &self.registers);
+        }
+        self.cycle += 1;
+        let program = Rc::clone(&self.program);
+        if let Some(instruction) = program.get(self.program_counter) {
+            self.elapsed += 1;
+            if self.elapsed == instruction.info.cycles {
+                self.elapsed = 0;
+                self.execute(instruction);
+            }
+        }
+    }
+
+    /// Run until we've finished `max_cycles` cycles. The limit keeps a
+    /// program that loops forever from hanging us; an instruction that
+    /// would run past it is left unfinished.
+    pub fn run(&mut self, observers: &mut [&mut dyn Observer], max_cycles: usize) {
+        while self.cycle <= max_cycles {
+            self.tick(observers);
+        }
+    }
+
+    fn execute(&mut self, instruction: &Instruction) {
+        let value = |i: usize| self.registers.value(instruction.operands[i]);
+        let mut offset = 1;
+        match instruction.info.opcode {
+            Opcode::Noop => {}
+            Opcode::AddX => self.registers.0[X.0] += value(0),
+            Opcode::Add | Opcode::Set => {
+                let result = match instruction.info.opcode {
+                    Opcode::Add => value(0) + value(1),
+                    _ => value(1),
+                };
+                if let Operand::Register(register) = instruction.operands[0] {
+                    self.registers.0[register.0] = result;
+                }
+            }
+            Opcode::Jump => offset = value(0),
+            Opcode::JumpIfNotZero => {
+                if value(0) != 0 {
+                    offset = value(1);
+                }
+            }
+        }
+        // Jumping before the start of the program halts it, just like
+        // jumping past the end does.
+        self.program_counter = self
+            .program_counter
+            .checked_add_signed(offset)
+            .unwrap_or(usize::MAX);
+    }
+}
+
+/// Parse a program, one instruction per line.
+///
+/// # Errors
+///
+/// If any of the lines isn't a valid instruction.
+pub fn parse_program(contents: &str) -> Result<Vec<Instruction>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse()
+                .with_context(|| format!("Failed to parse line {}", i + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Remembers the value of `x` during every cycle it sees.
+    impl Observer for Vec<isize> {
+        fn observe(&mut self, _: usize, registers: &Registers) {
+            self.push(registers.get(X));
+        }
+    }
+
+    #[test]
+    fn jumps_and_registers() -> Result<()> {
+        // Counts `y` down from 3, adding 2 to `x` each time around.
+        let program = parse_program("set y 3\naddx 2\nadd y -1\njnz y -2\nset z x")?;
+        let mut vm = Vm::new(program);
+        // 1 for `set`, then 3 times around 2 + 2 + 1, then 1 for the last `set`.
+        vm.run(&mut [], 17);
+        assert!(vm.is_halted());
+        assert_eq!("x=7 y=0 z=7 w=0", vm.registers().to_string());
+        assert_eq!(18, vm.cycle());
+        Ok(())
+    }
+
+    #[test]
+    fn infinite_loop_stops_at_limit() -> Result<()> {
+        let program = parse_program("addx 1\njmp -1")?;
+        let mut vm = Vm::new(program);
+        vm.run(&mut [], 10);
+        assert!(!vm.is_halted());
+        assert_eq!(11, vm.cycle());
+        Ok(())
+    }
+
+    #[test]
+    fn idles_after_halting() -> Result<()> {
+        let program = parse_program("addx 3")?;
+        let mut xs = Vec::new();
+        let mut vm = Vm::new(program);
+        vm.run(&mut [&mut xs], 5);
+        assert_eq!(vec![1, 1, 3 + 1, 3 + 1, 3 + 1], xs);
+        assert_eq!(None, vm.current_instruction().map(ToString::to_string));
+        Ok(())
+    }
+
+    #[test]
+    fn remaining_cycles() -> Result<()> {
+        let mut vm = Vm::new(parse_program("addx 3\nnoop")?);
+        assert_eq!(2, vm.remaining_cycles());
+        vm.tick(&mut []);
+        assert_eq!(1, vm.remaining_cycles());
+        vm.tick(&mut []);
+        assert_eq!((1, 1), (vm.program_counter(), vm.remaining_cycles()));
+        Ok(())
+    }
+
+    #[test]
+    fn bad_instructions() {
+        assert!(parse_program("addx").is_err());
+        assert!(parse_program("add 3 x").is_err());
+        assert!(parse_program("set q 1").is_err());
+        assert!(parse_program("mul x 2").is_err());
+        assert!(parse_program("noop 1").is_err());
+    }
+}
@@ -0,0 +1,410 @@
+// Recording and replaying the step-by-step history of a simulation (the
+// sand in day 14, the rocks in day 17, etc.), so we can go back and look
+// at any step without rerunning everything from scratch.
+//
+// A state is described by a set of lines (one per occupied position, say),
+// and each step of a history file only holds the lines that were added or
+// removed since the previous step:
+//
+//     step 0
+//     +498,4
+//     +498,5
+//     step 1
+//     +500,8
+//
+// The first step is always the full initial state.
+
+use anyhow::{bail, ensure, Context, Result};
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// A simulation state that can be recorded and later restored.
+pub trait Snapshot {
+    /// Describe the state as a set of single-line entries.
+    fn entries(&self) -> BTreeSet<String>;
+
+    /// Replace the recorded parts of the state with the given entries. This is
+    /// called on a freshly constructed state, so anything that isn't recorded
+    /// (e.g., the monkeys' operations in day 11) comes from the puzzle input.
+    ///
+    /// # Errors
+    ///
+    /// If any of the entries can't be parsed.
+    fn restore(&mut self, entries: &BTreeSet<String>) -> Result<()>;
+}
+
+/// Writes the diffs between successive states to a history file. If there's
+/// no file, recording does nothing, so the simulations can call `record`
+/// unconditionally.
+pub struct Recorder {
+    writer: Option<BufWriter<File>>,
+    previous: BTreeSet<String>,
+    num_steps: usize,
+}
+
+impl Recorder {
+    /// # Errors
+    ///
+    /// If the history file can't be created.
+    pub fn new(path: Option<&Path>) -> Result<Self> {
+        let writer = path
+            .map(|path| {
+                File::create(path)
+                    .with_context(|| format!("Failed to create file '{}'", path.display()))
+            })
+            .transpose()?
+            .map(BufWriter::new);
+        Ok(Self {
+            writer,
+            previous: BTreeSet::new(),
+            num_steps: 0,
+        })
+    }
+
+    /// # Errors
+    ///
+    /// If writing to the history file fails.
+    pub fn record(&mut self, state: &impl Snapshot) -> Result<()> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+        let current = state.entries();
+        writeln!(writer, "step {}", self.num_steps)?;
+        for removed in self.previous.difference(&current) {
+            writeln!(writer, "-{removed}")?;
+        }
+        for added in current.difference(&self.previous) {
+            writeln!(writer, "+{added}")?;
+        }
+        self.previous = current;
+        self.num_steps += 1;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// If flushing the history file fails.
+    pub fn finish(self) -> Result<()> {
+        if let Some(mut writer) = self.writer {
+            writer.flush()?;
+            println!("Recorded {} steps", self.num_steps);
+        }
+        Ok(())
+    }
+}
+
+/// The entries that were added and removed in going from one state to another.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub added: BTreeSet<String>,
+    pub removed: BTreeSet<String>,
+}
+
+impl Diff {
+    fn between(from: &BTreeSet<String>, to: &BTreeSet<String>) -> Self {
+        Self {
+            added: to.difference(from).cloned().collect(),
+            removed: from.difference(to).cloned().collect(),
+        }
+    }
+
+    fn apply(&self, entries: &mut BTreeSet<String>) {
+        for removed in &self.removed {
+            entries.remove(removed);
+        }
+        entries.extend(self.added.iter().cloned());
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for removed in &self.removed {
+            writeln!(f, "-{removed}")?;
+        }
+        for added in &self.added {
+            writeln!(f, "+{added}")?;
+        }
+        write!(
+            f,
+            "{} added, {} removed",
+            self.added.len(),
+            self.removed.len()
+        )
+    }
+}
+
+/// A history read back in from a file written by a `Recorder`.
+#[derive(Debug)]
+pub struct History {
+    steps: Vec<Diff>,
+}
+
+impl FromStr for History {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut steps: Vec<Diff> = Vec::new();
+        for (line_number, line) in s.lines().enumerate() {
+            let line_number = line_number + 1;
+            if let Some(step) = line.strip_prefix("step ") {
+                let step = step
+                    .parse::<usize>()
+                    .with_context(|| format!("Bad step number on line {line_number}"))?;
+                ensure!(
+                    step == steps.len(),
+                    "Expected step {} but found step {step} on line {line_number}",
+                    steps.len()
+                );
+                steps.push(Diff::default());
+                continue;
+            }
+            let diff = steps
+                .last_mut()
+                .with_context(|| format!("Line {line_number} comes before the first step"))?;
+            if let Some(added) = line.strip_prefix('+') {
+                diff.added.insert(added.to_string());
+            } else if let Some(removed) = line.strip_prefix('-') {
+                diff.removed.insert(removed.to_string());
+            } else {
+                bail!("Line {line_number} ('{line}') doesn't start with '+' or '-'");
+            }
+        }
+        Ok(Self { steps })
+    }
+}
+
+impl History {
+    /// # Errors
+    ///
+    /// If the file can't be read or isn't a valid history.
+    pub fn load(path: &Path) -> Result<Self> {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to open file '{}'", path.display()))?
+            .parse()
+            .with_context(|| format!("Failed to parse history file '{}'", path.display()))
+    }
+
+    #[must_use]
+    // A `const` `Vec::len` would need Rust 1.87.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn num_steps(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// The entries describing the state after the given step.
+    ///
+    /// # Errors
+    ///
+    /// If the history doesn't have that many steps.
+    pub fn seek(&self, step: usize) -> Result<BTreeSet<String>> {
+        ensure!(
+            step < self.steps.len(),
+            "There are only {} steps (0 through {}), so we can't seek to step {step}",
+            self.steps.len(),
+            self.steps.len().saturating_sub(1)
+        );
+        let mut entries = BTreeSet::new();
+        for diff in &self.steps[..=step] {
+            diff.apply(&mut entries);
+        }
+        Ok(entries)
+    }
+
+    /// # Errors
+    ///
+    /// If the history doesn't have either of those steps.
+    pub fn diff(&self, from: usize, to: usize) -> Result<Diff> {
+        Ok(Diff::between(&self.seek(from)?, &self.seek(to)?))
+    }
+}
+
+/// What a simulation that supports histories was asked to do on the
+/// command line:
+///
+///   `[--record <file>]` runs the simulation (optionally recording it),
+///   `--seek <file> <step>` prints the state after that step, and
+///   `--diff <file> <from> <to>` prints what changed between two steps.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Mode {
+    Run {
+        record: Option<PathBuf>,
+    },
+    Seek {
+        history: PathBuf,
+        step: usize,
+    },
+    Diff {
+        history: PathBuf,
+        from: usize,
+        to: usize,
+    },
+}
+
+fn parse_step(s: &str) -> Result<usize> {
+    s.parse::<usize>()
+        .with_context(|| format!("Couldn't parse '{s}' to a step number"))
+}
+
+impl Mode {
+    /// # Errors
+    ///
+    /// If the arguments don't match any of the modes.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Self> {
+        let args = args.collect::<Vec<_>>();
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        Ok(match args.as_slice() {
+            [] => Self::Run { record: None },
+            ["--record", file] => Self::Run {
+                record: Some(file.into()),
+            },
+            ["--seek", file, step] => Self::Seek {
+                history: file.into(),
+                step: parse_step(step)?,
+            },
+            ["--diff", file, from, to] => Self::Diff {
+                history: file.into(),
+                from: parse_step(from)?,
+                to: parse_step(to)?,
+            },
+            _ => {
+                bail!("Usage: [--record <file>] | --seek <file> <step> | --diff <file> <from> <to>")
+            }
+        })
+    }
+
+    /// Handle the `Seek` and `Diff` modes, using `state` (the initial state
+    /// from the puzzle input) to restore and render the recorded state. Returns the
+    /// file to record to (if any) when we're supposed to actually run the
+    /// simulation, and `None` if there's nothing left to do.
+    ///
+    /// # Errors
+    ///
+    /// If the history can't be loaded or doesn't have the requested steps.
+    pub fn replay<S: Snapshot + Display>(self, state: &mut S) -> Result<Option<Recorder>> {
+        match self {
+            Self::Run { record } => return Recorder::new(record.as_deref()).map(Some),
+            Self::Seek { history, step } => {
+                let history = History::load(&history)?;
+                state.restore(&history.seek(step)?)?;
+                println!("After step {step} of {}:\n{state}", history.num_steps() - 1);
+            }
+            Self::Diff { history, from, to } => {
+                let history = History::load(&history)?;
+                println!(
+                    "From step {from} to step {to}:\n{}",
+                    history.diff(from, to)?
+                );
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(entries: &[&str]) -> BTreeSet<String> {
+        entries.iter().map(ToString::to_string).collect()
+    }
+
+    static HISTORY: &str = "\
+step 0
++a
++b
+step 1
++c
+step 2
+-a
++d
+";
+
+    #[test]
+    fn seek() -> Result<()> {
+        let history: History = HISTORY.parse()?;
+        assert_eq!(3, history.num_steps());
+        assert_eq!(set(&["a", "b"]), history.seek(0)?);
+        assert_eq!(set(&["a", "b", "c"]), history.seek(1)?);
+        assert_eq!(set(&["b", "c", "d"]), history.seek(2)?);
+        assert!(history.seek(3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn diff() -> Result<()> {
+        let history: History = HISTORY.parse()?;
+        let diff = history.diff(0, 2)?;
+        assert_eq!(set(&["c", "d"]), diff.added);
+        assert_eq!(set(&["a"]), diff.removed);
+        // Going backwards just swaps the two sides.
+        let diff = history.diff(2, 0)?;
+        assert_eq!(set(&["a"]), diff.added);
+        assert_eq!(set(&["c", "d"]), diff.removed);
+        Ok(())
+    }
+
+    struct Entries(BTreeSet<String>);
+
+    impl Snapshot for Entries {
+        fn entries(&self) -> BTreeSet<String> {
+            self.0.clone()
+        }
+
+        fn restore(&mut self, entries: &BTreeSet<String>) -> Result<()> {
+            self.0.clone_from(entries);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_and_load() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("history_test_{}.txt", std::process::id()));
+        let mut recorder = Recorder::new(Some(&path))?;
+        for state in [
+            set(&["a", "b"]),
+            set(&["a", "b", "c"]),
+            set(&["b", "c", "d"]),
+        ] {
+            recorder.record(&Entries(state))?;
+        }
+        recorder.finish()?;
+        let contents = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+        assert_eq!(HISTORY, contents);
+        Ok(())
+    }
+
+    #[test]
+    fn bad_histories() {
+        assert!("+a".parse::<History>().is_err());
+        assert!("step 0\na".parse::<History>().is_err());
+        assert!("step 0\nstep 2".parse::<History>().is_err());
+    }
+
+    #[test]
+    fn modes() -> Result<()> {
+        let args = |args: &[&str]| {
+            args.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        assert_eq!(Mode::Run { record: None }, Mode::from_args(args(&[]))?);
+        assert_eq!(
+            Mode::Diff {
+                history: "h.txt".into(),
+                from: 3,
+                to: 5
+            },
+            Mode::from_args(args(&["--diff", "h.txt", "3", "5"]))?
+        );
+        assert!(Mode::from_args(args(&["--seek", "h.txt"])).is_err());
+        Ok(())
+    }
+}
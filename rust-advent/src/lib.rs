@@ -0,0 +1,14 @@
+#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+#![warn(clippy::unwrap_used)]
+#![warn(clippy::expect_used)]
+
+// Code shared by several of the binaries in `src/bin`. Each day is still
+// its own binary; this is just for the tooling (and the day 10 CPU and
+// day 11 monkeys) that would otherwise have to be copied into every binary
+// that uses it.
+
+pub mod cpu;
+pub mod history;
+pub mod monkeys;
+pub mod profile;
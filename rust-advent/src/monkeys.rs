@@ -0,0 +1,943 @@
+// The monkeys from day 11, shared by both parts. Part 1 and part 2 differ
+// only in how the worry levels are kept in check (the `Relief`) and how
+// many rounds there are, so both binaries run the same engine with
+// different `Options`.
+
+use crate::history::{Mode, Snapshot};
+use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, multispace0, u64},
+    combinator::{all_consuming, map, map_res},
+    multi::{many1, separated_list0},
+    sequence::{delimited, preceded},
+    IResult,
+};
+use num_bigint::BigUint;
+use std::{collections::BTreeSet, fmt::Display, mem, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// The right-hand side of a monkey's operation, e.g., `old * (old + 3)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    Old,
+    Constant(u64),
+    Binary(Box<Self>, Operator, Box<Self>),
+}
+
+/*
+ * We can safely mod by the Least Common Multiple (LCM)
+ * of all the things we mod by in the tests because that
+ * won't change the value of any of those mod calculations.
+ * This allows us to keep the size of the worry level under
+ * control.
+ *
+ * Subtraction works fine mod the LCM (we get the right remainder
+ * even if the "real" worry level would be negative), but division
+ * doesn't, so with a modulus any division is an error. Without a
+ * modulus the arithmetic is exact, and we fail rather than overflow,
+ * go negative, or lose a remainder.
+ */
+trait Worry: Clone + Display + From<u64> {
+    fn parse(s: &str) -> Result<Self>;
+
+    /// `None` if the result can't be computed exactly.
+    fn combine(&self, operator: Operator, right: &Self, modulus: Option<u64>) -> Option<Self>;
+
+    #[must_use]
+    fn reduce(self, modulus: Option<u64>) -> Self;
+
+    fn is_divisible_by(&self, divisor: u64) -> bool;
+
+    /// Divide, rounding down, as the monkeys do when we're relieved.
+    #[must_use]
+    fn divide_by(&self, divisor: u64) -> Self;
+}
+
+impl Worry for u64 {
+    fn parse(s: &str) -> Result<Self> {
+        s.parse()
+            .with_context(|| format!("Couldn't parse '{s}' to a worry level"))
+    }
+
+    fn combine(&self, operator: Operator, right: &Self, modulus: Option<u64>) -> Option<Self> {
+        let (left, right) = (*self, *right);
+        match (operator, modulus) {
+            (Operator::Add, None) => left.checked_add(right),
+            (Operator::Subtract, None) => left.checked_sub(right),
+            (Operator::Multiply, None) => left.checked_mul(right),
+            (Operator::Divide, None) => (right != 0 && left % right == 0).then(|| left / right),
+            (Operator::Add, Some(m)) => {
+                Self::try_from((u128::from(left) + u128::from(right)) % u128::from(m)).ok()
+            }
+            (Operator::Subtract, Some(m)) => {
+                let (left, right, m) = (u128::from(left), u128::from(right), u128::from(m));
+                Self::try_from((left + m - right % m) % m).ok()
+            }
+            (Operator::Multiply, Some(m)) => {
+                Self::try_from(u128::from(left) * u128::from(right) % u128::from(m)).ok()
+            }
+            (Operator::Divide, Some(_)) => None,
+        }
+    }
+
+    fn reduce(self, modulus: Option<u64>) -> Self {
+        modulus.map_or(self, |m| self % m)
+    }
+
+    // `is_multiple_of` would need Rust 1.87.
+    #[allow(clippy::manual_is_multiple_of)]
+    fn is_divisible_by(&self, divisor: u64) -> bool {
+        self % divisor == 0
+    }
+
+    fn divide_by(&self, divisor: u64) -> Self {
+        self / divisor
+    }
+}
+
+// Worry levels that can't overflow, so we can check that the other
+// policies don't change the answer (at least for a few rounds, before
+// the numbers get out of hand).
+impl Worry for BigUint {
+    fn parse(s: &str) -> Result<Self> {
+        s.parse()
+            .with_context(|| format!("Couldn't parse '{s}' to a worry level"))
+    }
+
+    fn combine(&self, operator: Operator, right: &Self, modulus: Option<u64>) -> Option<Self> {
+        let zero = Self::default();
+        let result = match (operator, modulus) {
+            (Operator::Add, _) => self + right,
+            (Operator::Subtract, None) if self >= right => self - right,
+            (Operator::Subtract, Some(m)) => self + m - right % m,
+            (Operator::Multiply, _) => self * right,
+            (Operator::Divide, None) if *right != zero && self % right == zero => self / right,
+            _ => return None,
+        };
+        Some(result.reduce(modulus))
+    }
+
+    fn reduce(self, modulus: Option<u64>) -> Self {
+        match modulus {
+            Some(m) => &self % m,
+            None => self,
+        }
+    }
+
+    fn is_divisible_by(&self, divisor: u64) -> bool {
+        self % divisor == Self::default()
+    }
+
+    fn divide_by(&self, divisor: u64) -> Self {
+        self / divisor
+    }
+}
+
+impl Expression {
+    fn evaluate<W: Worry>(&self, old: &W, modulus: Option<u64>) -> Result<W> {
+        let (left, operator, right) = match self {
+            Self::Old => return Ok(old.clone().reduce(modulus)),
+            Self::Constant(value) => return Ok(W::from(*value).reduce(modulus)),
+            Self::Binary(left, operator, right) => (left, *operator, right),
+        };
+        let left = left.evaluate(old, modulus)?;
+        let right = right.evaluate(old, modulus)?;
+        left.combine(operator, &right, modulus)
+            .with_context(|| format!("Can't compute {left} {operator} {right} exactly"))
+    }
+
+    // How the puzzle describes applying this operation, e.g., "is
+    // multiplied by 19", falling back on the whole expression when it's
+    // not one of the puzzle's simple forms.
+    fn describe(&self) -> String {
+        let Self::Binary(left, operator, right) = self else {
+            return format!("becomes {self}");
+        };
+        let verb = match operator {
+            Operator::Add => "increases by",
+            Operator::Subtract => "decreases by",
+            Operator::Multiply => "is multiplied by",
+            Operator::Divide => "is divided by",
+        };
+        match (&**left, &**right) {
+            (Self::Old, Self::Old) => format!("{verb} itself"),
+            (Self::Old, Self::Constant(value)) => format!("{verb} {value}"),
+            _ => format!("becomes {self}"),
+        }
+    }
+
+    fn divides(&self) -> bool {
+        match self {
+            Self::Old | Self::Constant(_) => false,
+            Self::Binary(left, operator, right) => {
+                *operator == Operator::Divide || left.divides() || right.divides()
+            }
+        }
+    }
+
+    // The only thing we can check ahead of time is dividing by a constant
+    // zero; anything else depends on the worry levels.
+    fn validate(&self) -> Result<()> {
+        if let Self::Binary(left, operator, right) = self {
+            ensure!(
+                !(*operator == Operator::Divide && **right == Self::Constant(0)),
+                "The operation '{self}' divides by zero"
+            );
+            left.validate()?;
+            right.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Self::Add => '+',
+            Self::Subtract => '-',
+            Self::Multiply => '*',
+            Self::Divide => '/',
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+// Sub-expressions are always wrapped in parentheses, so this doesn't
+// need to know anything about precedence.
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Old => write!(f, "old"),
+            Self::Constant(value) => write!(f, "{value}"),
+            Self::Binary(left, operator, right) => {
+                let operand = |e: &Self| match e {
+                    Self::Binary(..) => format!("({e})"),
+                    _ => e.to_string(),
+                };
+                write!(f, "{} {operator} {}", operand(left), operand(right))
+            }
+        }
+    }
+}
+
+/// A monkey's notes, including the items it starts out with; the items
+/// it's holding as things go along are in the `MonkeyState`.
+#[derive(Debug)]
+pub struct Monkey {
+    items: Vec<u64>,
+    operation: Expression,
+    test_value: u64,
+    true_target: usize,
+    false_target: usize,
+}
+
+/*
+Monkey 0:
+  Starting items: 71, 86
+  Operation: new = old * 13
+  Test: divisible by 19
+    If true: throw to monkey 6
+    If false: throw to monkey 7
+
+The parser allows any amount of whitespace (including none, and
+newlines) between tokens, and the monkeys can come in any order.
+ */
+fn ws<'a, O>(
+    inner: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(multispace0, inner, multispace0)
+}
+
+// Match each of the space-separated words in `words`, with any
+// whitespace between them.
+fn phrase<'a>(words: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, ()> {
+    move |mut s| {
+        for word in words.split_ascii_whitespace() {
+            (s, _) = ws(tag(word))(s)?;
+        }
+        Ok((s, ()))
+    }
+}
+
+fn factor(s: &str) -> IResult<&str, Expression> {
+    ws(alt((
+        map(tag("old"), |_| Expression::Old),
+        map(u64, Expression::Constant),
+        delimited(char('('), expression, char(')')),
+    )))(s)
+}
+
+// A left-associative chain of `operand`s separated by `operator`s.
+fn binary<'a>(
+    mut operand: impl FnMut(&'a str) -> IResult<&'a str, Expression>,
+    mut operator: impl FnMut(&'a str) -> IResult<&'a str, Operator>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Expression> {
+    move |s| {
+        let (mut s, mut left) = operand(s)?;
+        while let Ok((rest, operator)) = operator(s) {
+            let (rest, right) = operand(rest)?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+            s = rest;
+        }
+        Ok((s, left))
+    }
+}
+
+fn term(s: &str) -> IResult<&str, Expression> {
+    binary(
+        factor,
+        alt((
+            map(char('*'), |_| Operator::Multiply),
+            map(char('/'), |_| Operator::Divide),
+        )),
+    )(s)
+}
+
+fn expression(s: &str) -> IResult<&str, Expression> {
+    binary(
+        term,
+        alt((
+            map(char('+'), |_| Operator::Add),
+            map(char('-'), |_| Operator::Subtract),
+        )),
+    )(s)
+}
+
+fn monkey(s: &str) -> IResult<&str, (usize, Monkey)> {
+    let (s, number) = preceded(phrase("Monkey"), ws(usize))(s)?;
+    let (s, items) = preceded(
+        phrase(": Starting items :"),
+        separated_list0(ws(char(',')), ws(u64)),
+    )(s)?;
+    let (s, operation) = preceded(phrase("Operation : new ="), expression)(s)?;
+    let (s, test_value) = preceded(phrase("Test : divisible by"), ws(u64))(s)?;
+    let (s, true_target) = preceded(phrase("If true : throw to monkey"), ws(usize))(s)?;
+    let (s, false_target) = preceded(phrase("If false : throw to monkey"), ws(usize))(s)?;
+    Ok((
+        s,
+        (
+            number,
+            Monkey {
+                items,
+                operation,
+                test_value,
+                true_target,
+                false_target,
+            },
+        ),
+    ))
+}
+
+fn usize(s: &str) -> IResult<&str, usize> {
+    map_res(u64, usize::try_from)(s)
+}
+
+/// Parse all the monkeys' notes, returning the monkeys in order by
+/// their numbers.
+///
+/// Besides the grammar, we check that the numbers go
+/// from 0 with no gaps or repeats, that every monkey throws to some
+/// other monkey that exists, and that the tests and operations don't
+/// divide by zero.
+///
+/// # Errors
+///
+/// If the notes can't be parsed, or fail any of those checks.
+pub fn parse_monkeys(contents: &str) -> Result<Vec<Monkey>> {
+    let (_, mut monkeys) = all_consuming(many1(monkey))(contents)
+        .map_err(nom::Err::<nom::error::Error<&str>>::to_owned)
+        .context("Failed to parse the monkeys' notes")?;
+    monkeys.sort_by_key(|(number, _)| *number);
+    for (i, (number, monkey)) in monkeys.iter().enumerate() {
+        ensure!(
+            i == *number,
+            "Expected monkey {i}, but found monkey {number}; monkeys must be numbered from 0 without gaps or repeats"
+        );
+        ensure!(
+            monkey.test_value > 0,
+            "Monkey {number} tests for divisibility by 0"
+        );
+        for target in [monkey.true_target, monkey.false_target] {
+            ensure!(
+                target < monkeys.len() && target != *number,
+                "Monkey {number} throws to monkey {target}, which isn't another monkey"
+            );
+        }
+        monkey
+            .operation
+            .validate()
+            .with_context(|| format!("Illegal operation for monkey {number}"))?;
+    }
+    Ok(monkeys.into_iter().map(|(_, monkey)| monkey).collect())
+}
+
+/// How the worry levels are kept in check after each inspection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relief {
+    // Part 1: the monkey gets bored and the worry level is divided by
+    // `k`, rounding down.
+    DivideBy(u64),
+    // Part 2: no relief, but the worry levels are kept mod the LCM of the
+    // tests.
+    Lcm,
+    // No relief and no modulus, so the worry levels are exact (and get
+    // huge very quickly).
+    Exact,
+}
+
+impl FromStr for Relief {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "lcm" => Self::Lcm,
+            "exact" => Self::Exact,
+            _ => {
+                let divisor = s
+                    .strip_prefix("divide:")
+                    .with_context(|| format!("Unknown relief '{s}'"))?;
+                let divisor = divisor
+                    .parse()
+                    .with_context(|| format!("Couldn't parse the divisor '{divisor}'"))?;
+                ensure!(divisor > 0, "Can't divide worry levels by 0");
+                Self::DivideBy(divisor)
+            }
+        })
+    }
+}
+
+// Only build the line if we're actually tracing.
+fn log(trace: &mut Option<Vec<String>>, line: impl FnOnce() -> String) {
+    if let Some(trace) = trace {
+        trace.push(line());
+    }
+}
+
+const fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[derive(Debug)]
+struct MonkeyState<W> {
+    monkeys: Vec<Monkey>,
+    items: Vec<Vec<W>>,
+    inspection_count: Vec<usize>,
+    relief: Relief,
+    // The LCM of the tests if we're keeping worry levels mod the LCM.
+    modulus: Option<u64>,
+    // If we're tracing, the puzzle-style description of every inspection
+    // since the trace was last taken.
+    trace: Option<Vec<String>>,
+}
+
+impl<W: Worry> MonkeyState<W> {
+    fn new(monkeys: Vec<Monkey>, relief: Relief) -> Result<Self> {
+        let modulus = (relief == Relief::Lcm)
+            .then(|| {
+                monkeys
+                    .iter()
+                    .try_fold(1, |lcm: u64, m| {
+                        (lcm / gcd(lcm, m.test_value)).checked_mul(m.test_value)
+                    })
+                    .context("The LCM of the tests is too big for a u64; try `--relief exact`")
+            })
+            .transpose()?;
+        if modulus.is_some() {
+            if let Some(monkey) = monkeys.iter().position(|m| m.operation.divides()) {
+                bail!("Monkey {monkey}'s operation divides, so we can't keep worry levels mod the LCM");
+            }
+        }
+        Ok(Self {
+            items: monkeys
+                .iter()
+                .map(|m| m.items.iter().map(|&item| W::from(item)).collect())
+                .collect(),
+            inspection_count: vec![0; monkeys.len()],
+            monkeys,
+            relief,
+            modulus,
+            trace: None,
+        })
+    }
+
+    fn with_trace(self) -> Self {
+        Self {
+            trace: Some(Vec::new()),
+            ..self
+        }
+    }
+
+    fn take_trace(&mut self) -> Vec<String> {
+        self.trace.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Run `num_rounds` rounds, calling `after_round` with the round number
+    /// (starting at 1) and the state after each one.
+    fn process_rounds(
+        self,
+        num_rounds: usize,
+        mut after_round: impl FnMut(usize, &mut Self) -> Result<()>,
+    ) -> Result<Self> {
+        (1..=num_rounds).try_fold(self, |state, round| {
+            let mut state = state.process_round()?;
+            after_round(round, &mut state)?;
+            Ok(state)
+        })
+    }
+
+    fn process_round(self) -> Result<Self> {
+        let num_monkeys = self.monkeys.len();
+        (0..num_monkeys).try_fold(self, Self::process_monkey)
+    }
+
+    //  Monkey 0:
+    //   Monkey inspects an item with a worry level of 79.
+    //     Worry level is multiplied by 19 to 1501.
+    //     Monkey gets bored with item. Worry level is divided by 3 to 500.
+    //     Current worry level is not divisible by 23.
+    //     Item with worry level 500 is thrown to monkey 3.
+    //   Monkey inspects an item with a worry level of 98.
+    //     Worry level is multiplied by 19 to 1862.
+    //     Monkey gets bored with item. Worry level is divided by 3 to 620.
+    //     Current worry level is not divisible by 23.
+    //     Item with worry level 620 is thrown to monkey 3.
+
+    fn process_monkey(mut self, monkey_number: usize) -> Result<Self> {
+        log(&mut self.trace, || format!("Monkey {monkey_number}:"));
+        mem::take(&mut self.items[monkey_number])
+            .into_iter()
+            .try_fold(self, |ms, worry_level| {
+                ms.process_item(monkey_number, &worry_level)
+            })
+    }
+
+    // With the LCM relief, the worry levels in the trace are the ones mod
+    // the LCM.
+    fn process_item(mut self, monkey_number: usize, worry_level: &W) -> Result<Self> {
+        log(&mut self.trace, || {
+            format!("  Monkey inspects an item with a worry level of {worry_level}.")
+        });
+        let monkey = &self.monkeys[monkey_number];
+        let operation = &monkey.operation;
+        let mut worry_level = operation
+            .evaluate(worry_level, self.modulus)
+            .with_context(|| format!("Monkey {monkey_number} failed to compute '{operation}'"))?;
+        log(&mut self.trace, || {
+            format!("    Worry level {} to {worry_level}.", operation.describe())
+        });
+        if let Relief::DivideBy(divisor) = self.relief {
+            worry_level = worry_level.divide_by(divisor);
+            log(&mut self.trace, || {
+                format!("    Monkey gets bored with item. Worry level is divided by {divisor} to {worry_level}.")
+            });
+        }
+        let test_value = monkey.test_value;
+        let divisible = worry_level.is_divisible_by(test_value);
+        let target = if divisible {
+            monkey.true_target
+        } else {
+            monkey.false_target
+        };
+        log(&mut self.trace, || {
+            let not = if divisible { "" } else { "not " };
+            format!("    Current worry level is {not}divisible by {test_value}.")
+        });
+        log(&mut self.trace, || {
+            format!("    Item with worry level {worry_level} is thrown to monkey {target}.")
+        });
+        self.items[target].push(worry_level);
+        self.inspection_count[monkey_number] += 1;
+        Ok(self)
+    }
+
+    // The per-round report from the puzzle, e.g.,
+    //
+    // Monkey 0 inspected items 2 times.
+    // Monkey 1 inspected items 4 times.
+    fn inspection_table(&self) -> String {
+        self.inspection_count
+            .iter()
+            .enumerate()
+            .map(|(monkey_number, count)| {
+                format!("Monkey {monkey_number} inspected items {count} times.")
+            })
+            .join("\n")
+    }
+
+    fn monkey_business(&self) -> usize {
+        let mut counts = self.inspection_count.clone();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        counts.iter().take(2).product()
+    }
+}
+
+// This matches the format used in the puzzle description, e.g.,
+//
+// Monkey 0: 20, 23, 27, 26
+// Monkey 1: 2080, 25, 167, 207, 401, 1046
+// ...
+// Monkey 0 inspected items 101 times.
+// Monkey 1 inspected items 95 times.
+impl<W: Worry> Display for MonkeyState<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (monkey_number, items) in self.items.iter().enumerate() {
+            writeln!(f, "Monkey {monkey_number}: {}", items.iter().join(", "))?;
+        }
+        writeln!(f, "{}", self.inspection_table())
+    }
+}
+
+// We record the items each monkey holds and how many items it has
+// inspected so far; the operations and tests come from the input.
+impl<W: Worry> Snapshot for MonkeyState<W> {
+    fn entries(&self) -> BTreeSet<String> {
+        let items = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, items)| format!("items {i}: {}", items.iter().join(", ")));
+        let counts = self
+            .inspection_count
+            .iter()
+            .enumerate()
+            .map(|(i, count)| format!("inspected {i}: {count}"));
+        items.chain(counts).collect()
+    }
+
+    fn restore(&mut self, entries: &BTreeSet<String>) -> Result<()> {
+        for entry in entries {
+            let (key, value) = entry
+                .split_once(": ")
+                .with_context(|| format!("Missing ': ' in entry '{entry}'"))?;
+            let (kind, monkey_number) = key
+                .split_once(' ')
+                .with_context(|| format!("Missing monkey number in entry '{entry}'"))?;
+            let monkey_number = monkey_number.parse::<usize>()?;
+            match kind {
+                "items" if monkey_number < self.items.len() => {
+                    self.items[monkey_number] = value
+                        .split(", ")
+                        .filter(|s| !s.is_empty())
+                        .map(W::parse)
+                        .collect::<Result<_>>()?;
+                }
+                "inspected" if monkey_number < self.inspection_count.len() => {
+                    self.inspection_count[monkey_number] = value.parse()?;
+                }
+                _ => bail!("Illegal entry '{entry}'"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What to run: the relief and number of rounds, and whether to print
+/// the puzzle-style trace of every inspection and the per-round tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub relief: Relief,
+    pub num_rounds: usize,
+    pub trace: bool,
+    pub tables: bool,
+}
+
+impl Options {
+    #[must_use]
+    pub const fn part_1() -> Self {
+        Self {
+            relief: Relief::DivideBy(3),
+            num_rounds: 20,
+            trace: false,
+            tables: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn part_2() -> Self {
+        Self {
+            relief: Relief::Lcm,
+            num_rounds: 10_000,
+            trace: false,
+            tables: false,
+        }
+    }
+}
+
+// Like the puzzle, we report every round for the first 20, and then
+// every 1000th round. (`is_multiple_of` would need Rust 1.87.)
+#[allow(clippy::manual_is_multiple_of)]
+const fn is_report_round(round: usize) -> bool {
+    round <= 20 || round % 1000 == 0
+}
+
+fn run_with<W: Worry>(monkeys: Vec<Monkey>, options: &Options, mode: Mode) -> Result<()> {
+    let mut state = MonkeyState::<W>::new(monkeys, options.relief)?;
+    if options.trace {
+        state = state.with_trace();
+    }
+    let Some(mut recorder) = mode.replay(&mut state)? else {
+        return Ok(());
+    };
+    recorder.record(&state)?;
+
+    let final_state = state.process_rounds(options.num_rounds, |round, state| {
+        recorder.record(state)?;
+        for line in state.take_trace() {
+            println!("{line}");
+        }
+        if options.tables && is_report_round(round) {
+            println!("== After round {round} ==\n{}\n", state.inspection_table());
+        }
+        Ok(())
+    })?;
+    recorder.finish()?;
+
+    println!("The monkey business was {}", final_state.monkey_business());
+
+    Ok(())
+}
+
+/// Run the monkeys as described by `options`, printing the monkey business
+/// at the end. The history `mode` can record the run, or replay part of
+/// an earlier recording instead of running.
+///
+/// # Errors
+///
+/// If the monkeys can't be run with the given relief (e.g., an operation
+/// divides and we're keeping worry levels mod the LCM), a worry level
+/// can't be computed exactly, or the history fails.
+pub fn run(monkeys: Vec<Monkey>, options: &Options, mode: Mode) -> Result<()> {
+    match options.relief {
+        Relief::Exact => run_with::<BigUint>(monkeys, options, mode),
+        Relief::DivideBy(_) | Relief::Lcm => run_with::<u64>(monkeys, options, mode),
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use super::*;
+    use std::fs;
+
+    static TEST_INPUT_FILE: &str = "../inputs/day_11_test.input";
+
+    #[test]
+    fn example() -> Result<()> {
+        let monkeys = parse_monkeys(&fs::read_to_string(TEST_INPUT_FILE)?)?;
+        assert_eq!(4, monkeys.len());
+        assert_eq!(vec![79, 98], monkeys[0].items);
+        assert_eq!("old * old", monkeys[2].operation.to_string());
+        let final_state =
+            MonkeyState::<u64>::new(monkeys, Relief::Lcm)?.process_rounds(10_000, |_, _| Ok(()))?;
+        assert_eq!(2_713_310_158, final_state.monkey_business());
+        Ok(())
+    }
+
+    #[test]
+    fn whitespace_and_order() -> Result<()> {
+        let contents = "Monkey 1:Starting items:\n  Operation : new=old*old
+            Test: divisible   by 2 If true: throw to monkey 0 If false: throw to monkey 0
+
+\tMonkey 0:
+  Starting items: 4 ,5
+  Operation: new = 3 - old
+  Test: divisible by 3
+    If true: throw to monkey 1
+    If false: throw to monkey 1
+";
+        let monkeys = parse_monkeys(contents)?;
+        assert_eq!(vec![4, 5], monkeys[0].items);
+        assert!(monkeys[1].items.is_empty());
+        assert_eq!("old * old", monkeys[1].operation.to_string());
+        assert_eq!("3 - old", monkeys[0].operation.to_string());
+        Ok(())
+    }
+
+    fn parse_expression(s: &str) -> Result<Expression> {
+        let (_, expression) =
+            all_consuming(expression)(s).map_err(nom::Err::<nom::error::Error<&str>>::to_owned)?;
+        expression.validate()?;
+        Ok(expression)
+    }
+
+    #[test]
+    fn expressions() -> Result<()> {
+        let expression = parse_expression("2 * old + (old - 1) * 3 / old")?;
+        assert_eq!(
+            "(2 * old) + (((old - 1) * 3) / old)",
+            expression.to_string()
+        );
+        assert_eq!(8, expression.evaluate(&3_u64, None)?);
+        // 8 + 12 / 5 has a remainder.
+        assert!(expression.evaluate(&5_u64, None).is_err());
+        assert!(expression.evaluate(&0_u64, None).is_err());
+        assert!(expression.evaluate(&3_u64, Some(7)).is_err());
+        // The remainder is still right even though the result is negative.
+        assert_eq!(3, parse_expression("old - 10")?.evaluate(&6_u64, Some(7))?);
+        assert!(parse_expression("old - 10")?
+            .evaluate(&6_u64, None)
+            .is_err());
+        assert!(parse_expression("old / (2 - 2)")?
+            .evaluate(&6_u64, None)
+            .is_err());
+        assert!(parse_expression("old / 0").is_err());
+        assert!(parse_expression("old % 3").is_err());
+        assert!(parse_expression("(old + 1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bad_monkeys() {
+        let monkey = |number, true_target, false_target| {
+            format!(
+                "Monkey {number}: Starting items: 1 Operation: new = old Test: divisible by 2 \
+                 If true: throw to monkey {true_target} If false: throw to monkey {false_target}\n"
+            )
+        };
+        assert!(parse_monkeys(&(monkey(0, 1, 1) + &monkey(1, 0, 0))).is_ok());
+        // A gap, a repeated monkey, throwing to itself, and throwing to
+        // a monkey that doesn't exist.
+        assert!(parse_monkeys(&(monkey(0, 2, 2) + &monkey(2, 0, 0))).is_err());
+        assert!(parse_monkeys(&(monkey(0, 1, 1) + &monkey(0, 1, 1))).is_err());
+        assert!(parse_monkeys(&(monkey(0, 0, 1) + &monkey(1, 0, 0))).is_err());
+        assert!(parse_monkeys(&(monkey(0, 1, 5) + &monkey(1, 0, 0))).is_err());
+    }
+}
+
+#[cfg(test)]
+mod relief_tests {
+    use super::*;
+    use std::fs;
+
+    static TEST_INPUT_FILE: &str = "../inputs/day_11_test.input";
+
+    fn example_state<W: Worry>(relief: Relief) -> Result<MonkeyState<W>> {
+        let monkeys = parse_monkeys(&fs::read_to_string(TEST_INPUT_FILE)?)?;
+        MonkeyState::new(monkeys, relief)
+    }
+
+    #[test]
+    fn both_parts() -> Result<()> {
+        let part_1 =
+            example_state::<u64>(Relief::DivideBy(3))?.process_rounds(20, |_, _| Ok(()))?;
+        assert_eq!(10605, part_1.monkey_business());
+        assert_eq!(
+            "Monkey 0: 10, 12, 14, 26, 34\nMonkey 1: 245, 93, 53, 199, 115\nMonkey 2: \nMonkey 3: ",
+            part_1.to_string().lines().take(4).join("\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exact_matches_lcm() -> Result<()> {
+        let mut tables = Vec::new();
+        let lcm = example_state::<u64>(Relief::Lcm)?.process_rounds(20, |round, state| {
+            if round == 1 || round == 20 {
+                tables.push(state.inspection_table());
+            }
+            Ok(())
+        })?;
+        assert_eq!(Some(96_577), lcm.modulus);
+        let exact = example_state::<BigUint>(Relief::Exact)?.process_rounds(20, |_, _| Ok(()))?;
+        assert_eq!(lcm.inspection_count, exact.inspection_count);
+        assert_eq!(vec![99, 97, 8, 103], exact.inspection_count);
+        assert!(tables[0]
+            .starts_with("Monkey 0 inspected items 2 times.\nMonkey 1 inspected items 4 times."));
+        Ok(())
+    }
+
+    #[test]
+    fn true_lcm() -> Result<()> {
+        let contents = "Monkey 0: Starting items: 1 Operation: new = old Test: divisible by 4 \
+            If true: throw to monkey 1 If false: throw to monkey 1
+            Monkey 1: Starting items: Operation: new = old Test: divisible by 6 \
+            If true: throw to monkey 0 If false: throw to monkey 0";
+        let state = MonkeyState::<u64>::new(parse_monkeys(contents)?, Relief::Lcm)?;
+        assert_eq!(Some(12), state.modulus);
+        let dividing = contents.replace(
+            "new = old Test: divisible by 6",
+            "new = old / 2 Test: divisible by 6",
+        );
+        assert!(MonkeyState::<u64>::new(parse_monkeys(&dividing)?, Relief::Lcm).is_err());
+        assert!(MonkeyState::<u64>::new(parse_monkeys(&dividing)?, Relief::Exact).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn lcm_overflow() -> Result<()> {
+        // Three primes just under 2^32, so their LCM is way past 2^64.
+        let contents = [4_294_967_291_u64, 4_294_967_279, 4_294_967_231]
+            .iter()
+            .enumerate()
+            .map(|(i, prime)| {
+                let next = (i + 1) % 3;
+                format!(
+                    "Monkey {i}: Starting items: 1 Operation: new = old Test: divisible by {prime} \
+                    If true: throw to monkey {next} If false: throw to monkey {next}"
+                )
+            })
+            .join("\n");
+        assert!(MonkeyState::<u64>::new(parse_monkeys(&contents)?, Relief::Lcm).is_err());
+        assert!(MonkeyState::<u64>::new(parse_monkeys(&contents)?, Relief::Exact).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn subtract_more_than_modulus() {
+        // 5 - 100 = -95, which is 3 mod 7.
+        assert_eq!(Some(3), 5_u64.combine(Operator::Subtract, &100, Some(7)));
+        assert_eq!(
+            Some(BigUint::from(3_u64)),
+            BigUint::from(5_u64).combine(Operator::Subtract, &BigUint::from(100_u64), Some(7))
+        );
+        // The modulus can be big enough that `left + m` overflows a u64.
+        let m = u64::MAX - 1;
+        assert_eq!(
+            Some(m - 2),
+            (m - 1).combine(Operator::Subtract, &1, Some(m))
+        );
+    }
+
+    #[test]
+    fn trace() -> Result<()> {
+        let mut state = example_state::<u64>(Relief::DivideBy(3))?.with_trace();
+        state = state.process_round()?;
+        let trace = state.take_trace();
+        assert_eq!(
+            vec![
+                "Monkey 0:",
+                "  Monkey inspects an item with a worry level of 79.",
+                "    Worry level is multiplied by 19 to 1501.",
+                "    Monkey gets bored with item. Worry level is divided by 3 to 500.",
+                "    Current worry level is not divisible by 23.",
+                "    Item with worry level 500 is thrown to monkey 3.",
+            ],
+            trace[..6]
+        );
+        assert!(trace.contains(&"    Worry level is multiplied by itself to 6241.".to_string()));
+        assert!(state.take_trace().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_round_trip() -> Result<()> {
+        let state = example_state::<BigUint>(Relief::Exact)?.process_rounds(3, |_, _| Ok(()))?;
+        let mut restored = example_state::<BigUint>(Relief::Exact)?;
+        restored.restore(&state.entries())?;
+        assert_eq!(state.to_string(), restored.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+// Timing (and, with the `alloc-stats` feature, memory accounting) for the
+// phases of a solution, e.g.,
+//
+//     let valves = phase("parse", || parse_valves(&contents))?;
+//     let result = phase("part 1", || cave.max_release(...))?;
+//
+// prints something like
+//
+//     [parse] 52.1µs
+//     [part 1] 1.8s; 4096012 allocations, 1.2 GiB allocated, 402.3 MiB peak heap
+//
+// The memory numbers come from a counting global allocator that wraps the
+// system allocator, so they're only available when built with
+// `cargo run --release --features alloc-stats --bin ...`. Phases shouldn't
+// be nested, since each phase resets the peak.
+
+use std::time::{Duration, Instant};
+
+/// Run `f` as the named phase, printing how long it took (and how much it
+/// allocated, with the `alloc-stats` feature).
+pub fn phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start_stats = counting::start_phase();
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    match counting::end_phase(start_stats) {
+        Some(stats) => println!("[{name}] {}; {stats}", format_duration(elapsed)),
+        None => println!("[{name}] {}", format_duration(elapsed)),
+    }
+    result
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{duration:.1?}")
+}
+
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        fmt::Display,
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    };
+
+    static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+    static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+    static CURRENT_BYTES: AtomicUsize = AtomicUsize::new(0);
+    static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingAllocator;
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    fn record_allocation(size: usize) {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        ALLOCATED_BYTES.fetch_add(size, Relaxed);
+        let current = CURRENT_BYTES.fetch_add(size, Relaxed) + size;
+        PEAK_BYTES.fetch_max(current, Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = unsafe { System.alloc(layout) };
+            if !ptr.is_null() {
+                record_allocation(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = unsafe { System.alloc_zeroed(layout) };
+            if !ptr.is_null() {
+                record_allocation(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) };
+            CURRENT_BYTES.fetch_sub(layout.size(), Relaxed);
+        }
+
+        // We count a `realloc` as freeing the old block and allocating
+        // the new one, which is what it (at worst) does.
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+            if !new_ptr.is_null() {
+                CURRENT_BYTES.fetch_sub(layout.size(), Relaxed);
+                record_allocation(new_size);
+            }
+            new_ptr
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    pub struct Stats {
+        allocations: usize,
+        allocated_bytes: usize,
+        peak_bytes: usize,
+    }
+
+    pub fn start_phase() -> Stats {
+        PEAK_BYTES.store(CURRENT_BYTES.load(Relaxed), Relaxed);
+        Stats {
+            allocations: ALLOCATIONS.load(Relaxed),
+            allocated_bytes: ALLOCATED_BYTES.load(Relaxed),
+            peak_bytes: 0,
+        }
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn end_phase(start: Stats) -> Option<Stats> {
+        Some(Stats {
+            allocations: ALLOCATIONS.load(Relaxed) - start.allocations,
+            allocated_bytes: ALLOCATED_BYTES.load(Relaxed) - start.allocated_bytes,
+            peak_bytes: PEAK_BYTES.load(Relaxed),
+        })
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn format_bytes(bytes: usize) -> String {
+        const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+        let mut size = bytes as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{bytes} B")
+        } else {
+            format!("{size:.1} {}", UNITS[unit])
+        }
+    }
+
+    impl Display for Stats {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{} allocations, {} allocated, {} peak heap",
+                self.allocations,
+                format_bytes(self.allocated_bytes),
+                format_bytes(self.peak_bytes)
+            )
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn bytes() {
+            assert_eq!("12 B", format_bytes(12));
+            assert_eq!("1.5 KiB", format_bytes(1536));
+            assert_eq!("3.0 GiB", format_bytes(3 << 30));
+        }
+
+        #[test]
+        fn counts_allocations() {
+            let start = start_phase();
+            let v = vec![0u64; 1_000];
+            let stats = end_phase(start);
+            drop(v);
+            let Some(stats) = stats else {
+                unreachable!("We always have stats with the `alloc-stats` feature");
+            };
+            assert!(stats.allocations >= 1);
+            assert!(stats.allocated_bytes >= 8_000);
+            assert!(stats.peak_bytes >= 8_000);
+        }
+    }
+}
+
+// Without the `alloc-stats` feature we leave the system allocator alone
+// and only report timings.
+#[cfg(not(feature = "alloc-stats"))]
+mod counting {
+    pub struct Start;
+
+    pub const fn start_phase() -> Start {
+        Start
+    }
+
+    pub const fn end_phase(_: Start) -> Option<std::convert::Infallible> {
+        None
+    }
+}